@@ -133,12 +133,37 @@ pub fn lstrip_lines(input: TokenStream) -> proc_macro::TokenStream {
 
 /// Creates a [`SizedString`] from a string literal
 ///
+/// The length of the string is the length of the literal in bytes, once encoded as UTF-8.
+///
+/// Like [`concat!`], several literals can be provided, which are joined together. Byte string literals are also
+/// accepted, as long as the result is valid UTF-8.
+///
+/// If the first argument is an integer, and is followed by other literals, it is the capacity of the string, as in
+/// `sized_string!(32, "padded")`. The string is then padded to the capacity with NUL bytes. To start the string itself
+/// with a number, write the number as a string literal.
+///
 /// # Examples
 ///
 /// ```rust
+/// use quork::sized_string::SizedString;
+/// use quork_proc::sized_string;
+///
 /// let s = sized_string!("Hello, World!");
+/// let s: SizedString<6> = sized_string!("héllo");
+/// let s = sized_string!(b"bytes");
+///
+/// let s: SizedString<32> = sized_string!(32, "padded");
+/// assert_eq!(s.trimmed(), "padded");
+///
+/// // A leading integer is always the capacity, so a leading number is written as a string
+/// let s: SizedString<3> = sized_string!("1", '.', 2);
+/// assert_eq!(s, "1.2");
 /// ```
 #[proc_macro]
 pub fn sized_string(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
-    sized_string::sized_string(&syn::parse_macro_input!(input as LitStr)).into()
+    let input = parse_macro_input!(input as sized_string::SizedStringInput);
+
+    sized_string::sized_string(&input)
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
 }
@@ -1,14 +1,88 @@
 use proc_macro2::Span;
 use proc_macro_crate::FoundCrate;
-use syn::Ident;
+use syn::{
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    Ident, Lit, LitInt, Token,
+};
 
-pub fn sized_string(input: &syn::LitStr) -> proc_macro2::TokenStream {
-    let length = input.value().chars().count();
-    let chars = input
-        .value()
-        .chars()
-        .map(|c| quote::quote! { #c as u8 })
-        .collect::<Vec<_>>();
+pub struct SizedStringInput {
+    capacity: Option<LitInt>,
+    pieces: Vec<Lit>,
+}
+
+impl Parse for SizedStringInput {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut pieces: Vec<Lit> = Punctuated::<Lit, Token![,]>::parse_terminated(input)?
+            .into_iter()
+            .collect();
+
+        // A leading integer is the capacity, as long as something follows it.
+        // Strings starting with a number must use a string literal for it, such as `"1"`
+        let capacity = match pieces.first() {
+            Some(Lit::Int(capacity)) if pieces.len() > 1 => {
+                let capacity = capacity.clone();
+                pieces.remove(0);
+                Some(capacity)
+            }
+            _ => None,
+        };
+
+        if pieces.is_empty() {
+            return Err(input.error("expected at least one literal"));
+        }
+
+        Ok(Self { capacity, pieces })
+    }
+}
+
+/// Concatenates the literals into bytes, in the same way as [`concat!`]
+fn piece_bytes(piece: &Lit, bytes: &mut Vec<u8>) -> syn::Result<()> {
+    match piece {
+        Lit::Str(s) => bytes.extend_from_slice(s.value().as_bytes()),
+        Lit::ByteStr(s) => bytes.extend_from_slice(&s.value()),
+        Lit::Char(c) => bytes.extend_from_slice(c.value().to_string().as_bytes()),
+        Lit::Byte(b) => bytes.push(b.value()),
+        Lit::Int(i) => bytes.extend_from_slice(i.base10_digits().as_bytes()),
+        Lit::Float(f) => bytes.extend_from_slice(f.base10_digits().as_bytes()),
+        Lit::Bool(b) => bytes.extend_from_slice(b.value.to_string().as_bytes()),
+        _ => return Err(syn::Error::new(piece.span(), "unsupported literal")),
+    }
+
+    Ok(())
+}
+
+pub fn sized_string(input: &SizedStringInput) -> syn::Result<proc_macro2::TokenStream> {
+    let mut bytes = Vec::new();
+    for piece in &input.pieces {
+        piece_bytes(piece, &mut bytes)?;
+    }
+
+    if let Err(e) = std::str::from_utf8(&bytes) {
+        return Err(syn::Error::new(
+            input.pieces[0].span(),
+            format!("sized string is not valid UTF-8: {e}"),
+        ));
+    }
+
+    if let Some(capacity_lit) = &input.capacity {
+        let capacity = capacity_lit.base10_parse::<usize>()?;
+
+        if bytes.len() > capacity {
+            return Err(syn::Error::new(
+                capacity_lit.span(),
+                format!(
+                    "sized string is {} bytes long, which exceeds the capacity of {capacity} bytes",
+                    bytes.len()
+                ),
+            ));
+        }
+
+        // Pad with NUL bytes, as in a C string buffer
+        bytes.resize(capacity, 0);
+    }
+
+    let length = bytes.len();
 
     let quork_crate =
         match proc_macro_crate::crate_name("quork").expect("quork is present in `Cargo.toml`") {
@@ -19,9 +93,7 @@ pub fn sized_string(input: &syn::LitStr) -> proc_macro2::TokenStream {
             }
         };
 
-    let output = quote::quote! {
-        #quork_crate::sized_string::SizedString::<#length>::new([#(#chars),*])
-    };
-
-    output
+    Ok(quote::quote! {
+        #quork_crate::sized_string::SizedString::<#length>::new([#(#bytes),*])
+    })
 }
@@ -1,3 +1,6 @@
+// These tests deliberately cover the deprecated aliases
+#![allow(deprecated)]
+
 use quork_proc::{lstrip_lines, rstrip_lines, strip_lines};
 
 #[test]
@@ -1,8 +1,10 @@
 #![warn(clippy::pedantic)]
 #![warn(missing_docs)]
 #![doc = include_str!("../README.md")]
-#![cfg_attr(not(feature = "std"), no_std)]
+// Tests always have `std`, but the library is still built without it
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
 
+#[cfg(feature = "alloc")]
 extern crate alloc;
 
 cfg_if::cfg_if! {
@@ -47,11 +49,15 @@ pub mod network;
 #[cfg(feature = "sized_string")]
 pub mod sized_string;
 
+#[cfg(all(unix, feature = "shm"))]
+pub mod shm;
+
 cfg_if::cfg_if! {
     if #[cfg(all(feature = "root", feature = "std"))] {
         pub mod root;
     }
 }
 
+#[cfg(feature = "alloc")]
 /// Truncation helpers for truncating strings when formatting
 pub mod truncate;
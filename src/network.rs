@@ -1,7 +1,61 @@
 //! Network helpers
 
+#[cfg(any(windows, target_os = "linux"))]
+pub mod connectivity;
+pub mod dns;
+pub mod happy_eyeballs;
+#[cfg(unix)]
+pub mod interface;
+pub mod ip_network;
+#[cfg(target_os = "linux")]
+mod netlink;
+#[cfg(unix)]
+pub mod ping;
+#[cfg(target_os = "linux")]
+pub mod policy;
+pub mod portal;
+#[cfg(any(windows, target_os = "linux"))]
+pub mod provider;
+pub mod proxy;
+#[cfg(target_os = "linux")]
+pub mod route;
+#[cfg(target_os = "linux")]
+pub mod snapshot;
+#[cfg(target_os = "linux")]
+pub mod socket;
+pub mod stack;
+#[cfg(target_os = "linux")]
+pub mod stats;
+#[cfg(unix)]
+pub mod tunnel;
+#[cfg(target_os = "linux")]
+mod watch;
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    str::FromStr,
+};
+
+pub use happy_eyeballs::{connect, HappyEyeballs};
+#[cfg(unix)]
+pub use interface::{
+    interface_by_name, interfaces, primary_address, Interface, InterfaceAddress, PrimaryAddress,
+};
+pub use ip_network::IpNetwork;
+#[cfg(any(windows, target_os = "linux"))]
+pub use provider::{ConnectivityProvider, FakeProvider, SystemProvider};
+#[cfg(target_os = "linux")]
+pub use route::{default_route, route_to, routes, Route};
+pub use stack::StackSupport;
+#[cfg(target_os = "linux")]
+pub use tunnel::VpnStatus;
+#[cfg(unix)]
+pub use tunnel::{tunnels, TunnelKind};
+
 /// A list of the possible ip versions
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum IpVersion {
     /// IPv4
     V4,
@@ -9,5 +63,131 @@ pub enum IpVersion {
     V6,
 }
 
+impl IpVersion {
+    #[must_use]
+    /// Gets the unspecified address for this version (i.e `0.0.0.0` or `::`)
+    pub const fn unspecified(self) -> IpAddr {
+        match self {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+
+    #[must_use]
+    /// Gets the loopback address for this version (i.e `127.0.0.1` or `::1`)
+    pub const fn loopback(self) -> IpAddr {
+        match self {
+            IpVersion::V4 => IpAddr::V4(Ipv4Addr::LOCALHOST),
+            IpVersion::V6 => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        }
+    }
+
+    #[must_use]
+    /// Gets the length of an address of this version, in bits
+    pub const fn bits(self) -> u8 {
+        match self {
+            IpVersion::V4 => 32,
+            IpVersion::V6 => 128,
+        }
+    }
+
+    #[must_use]
+    /// Checks if this version is enabled on the host
+    ///
+    /// See [`StackSupport::detect`] for how this is determined.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            IpVersion::V4 => stack::ipv4_enabled(),
+            IpVersion::V6 => stack::ipv6_enabled(),
+        }
+    }
+}
+
+impl From<IpAddr> for IpVersion {
+    fn from(addr: IpAddr) -> Self {
+        match addr {
+            IpAddr::V4(_) => IpVersion::V4,
+            IpAddr::V6(_) => IpVersion::V6,
+        }
+    }
+}
+
+impl From<Ipv4Addr> for IpVersion {
+    fn from(_: Ipv4Addr) -> Self {
+        IpVersion::V4
+    }
+}
+
+impl From<Ipv6Addr> for IpVersion {
+    fn from(_: Ipv6Addr) -> Self {
+        IpVersion::V6
+    }
+}
+
+impl From<SocketAddr> for IpVersion {
+    fn from(addr: SocketAddr) -> Self {
+        addr.ip().into()
+    }
+}
+
+impl fmt::Display for IpVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IpVersion::V4 => "IPv4".fmt(f),
+            IpVersion::V6 => "IPv6".fmt(f),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid IP version: {0:?}")]
+/// The string is not a valid [`IpVersion`]
+pub struct ParseIpVersionError(String);
+
+impl FromStr for IpVersion {
+    type Err = ParseIpVersionError;
+
+    /// Parses an [`IpVersion`] from strings such as `IPv4`, `v6`, `4` or `inet6`, ignoring case
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ipv4" | "v4" | "4" | "inet" => Ok(IpVersion::V4),
+            "ipv6" | "v6" | "6" | "inet6" => Ok(IpVersion::V6),
+            _ => Err(ParseIpVersionError(s.to_string())),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+pub use crate::unix::network::Connectivity;
 #[cfg(windows)]
 pub use crate::win::network::*;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        assert_eq!(
+            IpVersion::from("10.0.0.1".parse::<IpAddr>().unwrap()),
+            IpVersion::V4
+        );
+        assert_eq!(
+            IpVersion::from("[::1]:80".parse::<SocketAddr>().unwrap()),
+            IpVersion::V6
+        );
+        assert_eq!(IpVersion::from(IpVersion::V6.loopback()), IpVersion::V6);
+        assert!(IpVersion::V4.unspecified().is_unspecified());
+    }
+
+    #[test]
+    fn test_display_from_str() {
+        for version in [IpVersion::V4, IpVersion::V6] {
+            assert_eq!(version.to_string().parse::<IpVersion>().unwrap(), version);
+        }
+
+        assert_eq!("inet6".parse::<IpVersion>().unwrap(), IpVersion::V6);
+        assert_eq!("4".parse::<IpVersion>().unwrap(), IpVersion::V4);
+        assert!("ipv5".parse::<IpVersion>().is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Waiting for network connectivity
+//!
+//! Waiting uses [`ConnectivityProvider::wait_for_change`] between checks, which for the system provider on Linux
+//! watches a netlink socket, so waiting returns as soon as the network comes up.
+//! Elsewhere the connectivity is polled, backing off up to [`MAX_POLL_INTERVAL`].
+
+use std::{
+    future::Future,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+    task::{Context, Poll, Waker},
+    time::{Duration, Instant},
+};
+
+use super::{
+    provider::{ConnectivityProvider, SystemProvider},
+    Connectivity,
+};
+
+/// The delay before the connectivity is first re-checked
+pub const INITIAL_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The longest delay between re-checking the connectivity
+pub const MAX_POLL_INTERVAL: Duration = Duration::from_secs(5);
+
+impl Connectivity {
+    #[must_use]
+    /// Checks if there is connectivity to the Internet
+    pub fn is_internet(&self) -> bool {
+        matches!(
+            self,
+            Connectivity::Ipv4Internet | Connectivity::Ipv6Internet
+        )
+    }
+
+    #[must_use]
+    /// Checks if there is connectivity to a routed network, which includes the Internet
+    pub fn is_routed(&self) -> bool {
+        self.is_internet()
+            || matches!(
+                self,
+                Connectivity::Ipv4Localnetwork | Connectivity::Ipv6Localnetwork
+            )
+    }
+
+    #[must_use]
+    /// Checks if there is connectivity to any network
+    pub fn is_connected(&self) -> bool {
+        *self != Connectivity::Disconnected
+    }
+
+    /// Blocks until the connectivity satisfies the predicate, or the timeout passes
+    ///
+    /// Returns the connectivity that satisfied the predicate, or `None` if the timeout passed.
+    /// Failures to get the current connectivity are retried until the timeout.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use std::time::Duration;
+    /// # use quork::network::{Connectivity, IpVersion};
+    /// let ipv4_internet = Connectivity::wait_until(
+    ///     |conn| conn.is_internet() && conn.ip_version() == Some(IpVersion::V4),
+    ///     Duration::from_secs(30),
+    /// );
+    ///
+    /// if ipv4_internet.is_none() {
+    ///     eprintln!("Starting without a network");
+    /// }
+    /// ```
+    pub fn wait_until(
+        predicate: impl FnMut(Connectivity) -> bool,
+        timeout: Duration,
+    ) -> Option<Connectivity> {
+        Self::wait_until_with(&SystemProvider::new(), predicate, timeout)
+    }
+
+    /// Blocks until the connectivity reported by the provider satisfies the predicate, or the timeout passes
+    ///
+    /// See [`Connectivity::wait_until`] for more information.
+    pub fn wait_until_with(
+        provider: &impl ConnectivityProvider,
+        predicate: impl FnMut(Connectivity) -> bool,
+        timeout: Duration,
+    ) -> Option<Connectivity> {
+        wait(provider, predicate, timeout, &AtomicBool::new(false))
+    }
+
+    /// Waits until the connectivity satisfies the predicate, or the timeout passes, without blocking
+    ///
+    /// The waiting is done on a background thread, so the returned future works with any async runtime.
+    /// Dropping the future stops the thread the next time it checks the connectivity.
+    ///
+    /// See [`Connectivity::wait_until`] for more information.
+    pub fn wait_until_async(
+        predicate: impl FnMut(Connectivity) -> bool + Send + 'static,
+        timeout: Duration,
+    ) -> WaitUntil {
+        Self::wait_until_async_with(SystemProvider::new(), predicate, timeout)
+    }
+
+    /// Waits until the connectivity reported by the provider satisfies the predicate, or the timeout passes, without
+    /// blocking
+    ///
+    /// See [`Connectivity::wait_until_async`] for more information.
+    pub fn wait_until_async_with(
+        provider: impl ConnectivityProvider + Send + 'static,
+        predicate: impl FnMut(Connectivity) -> bool + Send + 'static,
+        timeout: Duration,
+    ) -> WaitUntil {
+        let state = Arc::new(Mutex::new(WaitState::default()));
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let thread_state = state.clone();
+        let thread_cancelled = cancelled.clone();
+
+        std::thread::spawn(move || {
+            let result = wait(&provider, predicate, timeout, &thread_cancelled);
+
+            let mut state = thread_state.lock().unwrap_or_else(PoisonError::into_inner);
+            state.done = true;
+            state.result = result;
+            if let Some(waker) = state.waker.take() {
+                waker.wake();
+            }
+        });
+
+        WaitUntil { state, cancelled }
+    }
+}
+
+/// Waits until the connectivity satisfies the predicate, the timeout passes, or the wait is cancelled
+fn wait(
+    provider: &impl ConnectivityProvider,
+    mut predicate: impl FnMut(Connectivity) -> bool,
+    timeout: Duration,
+    cancelled: &AtomicBool,
+) -> Option<Connectivity> {
+    let deadline = Instant::now() + timeout;
+    let mut interval = INITIAL_POLL_INTERVAL;
+
+    while !cancelled.load(Ordering::Relaxed) {
+        if let Some(connectivity) = provider.connectivity() {
+            if predicate(connectivity) {
+                return Some(connectivity);
+            }
+        }
+
+        let remaining = deadline
+            .checked_duration_since(Instant::now())
+            .filter(|remaining| !remaining.is_zero())?;
+
+        provider.wait_for_change(interval.min(remaining));
+
+        interval = (interval * 2).min(MAX_POLL_INTERVAL);
+    }
+
+    None
+}
+
+#[derive(Debug, Default)]
+struct WaitState {
+    done: bool,
+    result: Option<Connectivity>,
+    waker: Option<Waker>,
+}
+
+#[derive(Debug)]
+#[must_use = "futures do nothing unless you `.await` or poll them"]
+/// A future returned by [`Connectivity::wait_until_async`]
+pub struct WaitUntil {
+    state: Arc<Mutex<WaitState>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Drop for WaitUntil {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+impl Future for WaitUntil {
+    type Output = Option<Connectivity>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+
+        if state.done {
+            Poll::Ready(state.result)
+        } else {
+            state.waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::Arc,
+        task::{Wake, Waker},
+        thread::Thread,
+    };
+
+    use super::*;
+
+    struct ThreadWaker(Thread);
+
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let waker = Waker::from(Arc::new(ThreadWaker(std::thread::current())));
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => std::thread::park(),
+            }
+        }
+    }
+
+    #[test]
+    fn test_levels() {
+        assert!(Connectivity::Ipv6Internet.is_internet());
+        assert!(Connectivity::Ipv4Internet.is_routed());
+        assert!(Connectivity::Ipv4Localnetwork.is_routed());
+        assert!(!Connectivity::Ipv4Subnet.is_routed());
+        assert!(Connectivity::Ipv6Subnet.is_connected());
+        assert!(!Connectivity::Disconnected.is_connected());
+    }
+
+    #[test]
+    fn test_wait_until() {
+        let current = Connectivity::get();
+
+        assert_eq!(
+            Connectivity::wait_until(|_| true, Duration::ZERO),
+            Some(current)
+        );
+
+        let start = Instant::now();
+        let mut checks = 0;
+        let result = Connectivity::wait_until(
+            |_| {
+                checks += 1;
+                false
+            },
+            Duration::from_millis(250),
+        );
+
+        assert_eq!(result, None);
+        assert!(start.elapsed() >= Duration::from_millis(250));
+        // Checked once up front, and again after each delay
+        assert!(checks >= 2);
+    }
+
+    #[test]
+    fn test_wait_until_async() {
+        let current = Connectivity::get();
+
+        assert_eq!(
+            block_on(Connectivity::wait_until_async(
+                |_| true,
+                Duration::from_secs(1)
+            )),
+            Some(current)
+        );
+        assert_eq!(
+            block_on(Connectivity::wait_until_async(
+                |_| false,
+                Duration::from_millis(100)
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn test_wait_until_async_drop() {
+        use std::sync::atomic::AtomicUsize;
+
+        use crate::network::FakeProvider;
+
+        let provider = Arc::new(FakeProvider::offline());
+        let checks = Arc::new(AtomicUsize::new(0));
+
+        let thread_checks = checks.clone();
+        let future = Connectivity::wait_until_async_with(
+            provider.clone(),
+            move |_| {
+                thread_checks.fetch_add(1, Ordering::Relaxed);
+                false
+            },
+            Duration::from_secs(60),
+        );
+
+        std::thread::sleep(Duration::from_millis(50));
+        drop(future);
+
+        // Wake the background thread, which should now stop rather than checking again
+        provider.set(Connectivity::Ipv4Internet);
+        std::thread::sleep(Duration::from_millis(200));
+
+        assert_eq!(checks.load(Ordering::Relaxed), 1);
+        assert_eq!(Arc::strong_count(&provider), 1);
+    }
+}
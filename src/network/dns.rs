@@ -0,0 +1,292 @@
+//! DNS resolver configuration
+//!
+//! Parses the resolver configuration from `resolv.conf`, in the format described by `resolv.conf(5)`.
+
+use std::{net::IpAddr, path::Path};
+
+use super::IpVersion;
+
+/// The default location of the resolver configuration
+pub const RESOLV_CONF_PATH: &str = "/etc/resolv.conf";
+
+/// The addresses the systemd-resolved stub resolver listens on
+const SYSTEMD_RESOLVED_STUBS: [IpAddr; 2] = [
+    IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 53)),
+    IpAddr::V4(std::net::Ipv4Addr::new(127, 0, 0, 54)),
+];
+
+/// The directory systemd-resolved manages its `resolv.conf` files in
+const SYSTEMD_RESOLVED_DIR: &str = "/run/systemd/resolve";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A nameserver from the resolver configuration
+pub struct Nameserver {
+    /// The address of the nameserver
+    pub addr: IpAddr,
+    /// The [`IpVersion`] of the nameserver's address
+    pub version: IpVersion,
+    /// The scope of a link-local IPv6 address (i.e `eth0` in `fe80::1%eth0`)
+    pub scope: Option<String>,
+}
+
+impl Nameserver {
+    fn parse(value: &str) -> Option<Self> {
+        let (addr, scope) = match value.split_once('%') {
+            Some((addr, scope)) => (addr, Some(scope.to_string())),
+            None => (value, None),
+        };
+
+        let addr: IpAddr = addr.parse().ok()?;
+
+        Some(Self {
+            addr,
+            version: addr.into(),
+            scope,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// Resolver options, set through the `options` keyword
+pub struct ResolverOptions {
+    /// The number of dots a name must contain before an initial absolute query is made
+    pub ndots: u8,
+    /// The number of seconds to wait for a response from a nameserver
+    pub timeout: u8,
+    /// The number of times to query the nameservers before giving up
+    pub attempts: u8,
+    /// Whether queries are round-robined between the nameservers
+    pub rotate: bool,
+    /// Whether EDNS0 extensions are enabled
+    pub edns0: bool,
+    /// Any other options, as they appear in the configuration
+    pub other: Vec<String>,
+}
+
+impl Default for ResolverOptions {
+    fn default() -> Self {
+        Self {
+            ndots: 1,
+            timeout: 5,
+            attempts: 2,
+            rotate: false,
+            edns0: false,
+            other: Vec::new(),
+        }
+    }
+}
+
+impl ResolverOptions {
+    fn apply(&mut self, option: &str) {
+        // Values above the limit are clamped to it, rather than ignored
+        let numeric = |value: &str, max: u8| {
+            value
+                .parse::<u64>()
+                .ok()
+                .map(|v| u8::try_from(v).map_or(max, |v| v.min(max)))
+        };
+
+        match option.split_once(':') {
+            // Limits taken from `resolv.conf(5)`
+            Some(("ndots", value)) => self.ndots = numeric(value, 15).unwrap_or(self.ndots),
+            Some(("timeout", value)) => self.timeout = numeric(value, 30).unwrap_or(self.timeout),
+            Some(("attempts", value)) => {
+                self.attempts = numeric(value, 5).unwrap_or(self.attempts);
+            }
+            None if option == "rotate" => self.rotate = true,
+            None if option == "edns0" => self.edns0 = true,
+            _ => self.other.push(option.to_string()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The parsed resolver configuration
+///
+/// # Examples
+///
+/// ```
+/// # use quork::network::dns::ResolvConf;
+/// let conf = ResolvConf::parse("
+/// # Generated by NetworkManager
+/// search example.com
+/// nameserver 1.1.1.1
+/// options ndots:2 rotate
+/// ");
+///
+/// assert_eq!(conf.search, ["example.com"]);
+/// assert_eq!(conf.options.ndots, 2);
+/// ```
+pub struct ResolvConf {
+    /// The configured nameservers, in order of preference
+    pub nameservers: Vec<Nameserver>,
+    /// The search list for hostname lookups
+    ///
+    /// The `domain` keyword is treated as a search list with a single entry, as per `resolv.conf(5)`
+    pub search: Vec<String>,
+    /// The resolver options
+    pub options: ResolverOptions,
+    /// Whether the configuration points to the systemd-resolved stub resolver
+    pub systemd_resolved_stub: bool,
+}
+
+impl ResolvConf {
+    #[must_use]
+    /// Parse the resolver configuration from the contents of a `resolv.conf` file
+    ///
+    /// As with the system resolver, unknown keywords and invalid values are ignored.
+    pub fn parse(content: &str) -> Self {
+        let mut conf = Self::default();
+
+        for line in content.lines() {
+            // Both `#` and `;` start comments, but only in the first column
+            if line.starts_with(['#', ';']) {
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+
+            let Some(keyword) = words.next() else {
+                continue;
+            };
+
+            match keyword {
+                "nameserver" => {
+                    if let Some(nameserver) = words.next().and_then(Nameserver::parse) {
+                        conf.nameservers.push(nameserver);
+                    }
+                }
+                // The last `domain` or `search` keyword overrides any before it
+                "domain" => {
+                    conf.search = words.next().map(ToString::to_string).into_iter().collect();
+                }
+                "search" => conf.search = words.map(ToString::to_string).collect(),
+                "options" => words.for_each(|option| conf.options.apply(option)),
+                _ => {}
+            }
+        }
+
+        conf.systemd_resolved_stub = !conf.nameservers.is_empty()
+            && conf
+                .nameservers
+                .iter()
+                .all(|ns| SYSTEMD_RESOLVED_STUBS.contains(&ns.addr));
+
+        conf
+    }
+
+    /// Read and parse the resolver configuration from the provided path
+    ///
+    /// If the path is a symlink into systemd-resolved's runtime directory, it is detected as a stub setup.
+    ///
+    /// # Errors
+    /// - The file could not be read
+    pub fn from_path(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let path = path.as_ref();
+        let mut conf = Self::parse(&std::fs::read_to_string(path)?);
+
+        if let Ok(target) = std::fs::canonicalize(path) {
+            conf.systemd_resolved_stub |= target.starts_with(SYSTEMD_RESOLVED_DIR)
+                && target
+                    .file_name()
+                    .is_some_and(|name| name == "stub-resolv.conf");
+        }
+
+        Ok(conf)
+    }
+
+    /// Read and parse the system resolver configuration from [`RESOLV_CONF_PATH`]
+    ///
+    /// # Errors
+    /// - The file could not be read
+    pub fn system() -> std::io::Result<Self> {
+        Self::from_path(RESOLV_CONF_PATH)
+    }
+
+    /// Gets the nameservers that use the provided [`IpVersion`]
+    pub fn nameservers_for(&self, version: IpVersion) -> impl Iterator<Item = &Nameserver> {
+        self.nameservers
+            .iter()
+            .filter(move |ns| ns.version == version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESOLV_CONF: &str = "
+# This is a comment
+; So is this
+domain old.example
+search corp.example example.com
+nameserver 192.168.1.1
+nameserver 2001:4860:4860::8888
+nameserver fe80::1%eth0
+nameserver not-an-address
+options ndots:5 timeout:300 attempts:3 rotate edns0 trust-ad
+";
+
+    #[test]
+    fn test_parse() {
+        let conf = ResolvConf::parse(RESOLV_CONF);
+
+        assert_eq!(conf.nameservers.len(), 3);
+        assert_eq!(conf.nameservers[0].version, IpVersion::V4);
+        assert_eq!(conf.nameservers[1].version, IpVersion::V6);
+        assert_eq!(conf.nameservers[2].scope.as_deref(), Some("eth0"));
+
+        assert_eq!(conf.search, ["corp.example", "example.com"]);
+
+        assert_eq!(conf.options.ndots, 5);
+        // Clamped to the maximum of 30
+        assert_eq!(conf.options.timeout, 30);
+        assert_eq!(conf.options.attempts, 3);
+        assert!(conf.options.rotate);
+        assert!(conf.options.edns0);
+        assert_eq!(conf.options.other, ["trust-ad"]);
+
+        assert!(!conf.systemd_resolved_stub);
+        assert_eq!(conf.nameservers_for(IpVersion::V6).count(), 2);
+    }
+
+    #[test]
+    fn test_comments() {
+        let conf = ResolvConf::parse(
+            "nameserver 1.1.1.1 # primary\n  ; indented\nsearch a.example;b.example",
+        );
+
+        // Comment characters only start a comment in the first column, as with glibc
+        assert_eq!(conf.nameservers.len(), 1);
+        assert_eq!(conf.search, ["a.example;b.example"]);
+    }
+
+    #[test]
+    fn test_defaults() {
+        let conf = ResolvConf::parse("");
+
+        assert!(conf.nameservers.is_empty());
+        assert_eq!(conf.options, ResolverOptions::default());
+    }
+
+    #[test]
+    fn test_systemd_resolved_stub() {
+        let conf = ResolvConf::parse("nameserver 127.0.0.53\noptions edns0 trust-ad\nsearch .");
+
+        assert!(conf.systemd_resolved_stub);
+    }
+
+    #[test]
+    fn test_from_path() {
+        let path = std::env::temp_dir().join(format!("quork-resolv-{}.conf", std::process::id()));
+        std::fs::write(&path, RESOLV_CONF).unwrap();
+
+        let conf = ResolvConf::from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(conf, ResolvConf::parse(RESOLV_CONF));
+    }
+}
@@ -0,0 +1,270 @@
+//! Happy Eyeballs ([RFC 8305](https://www.rfc-editor.org/rfc/rfc8305)) connections
+//!
+//! Rather than trying each resolved address in turn, and waiting for a broken IPv6 route to time out before
+//! falling back to IPv4, connection attempts are raced against each other with a short stagger between them.
+//!
+//! Unlike RFC 8305, names are resolved with the system resolver before the race starts, so a slow lookup for one
+//! [`IpVersion`] still delays connecting over the other. Pass resolved addresses to avoid this.
+
+use std::{
+    io,
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    sync::mpsc,
+    thread,
+    time::Duration,
+};
+
+use super::IpVersion;
+
+/// The default delay between starting connection attempts, as recommended by RFC 8305
+pub const DEFAULT_ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// The default timeout for each connection attempt
+///
+/// Attempts that lose the race keep running in the background until they connect, fail or time out.
+pub const DEFAULT_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+/// A configurable Happy Eyeballs connector
+///
+/// # Examples
+///
+/// ```no_run
+/// # use quork::network::{happy_eyeballs::HappyEyeballs, IpVersion};
+/// let stream = HappyEyeballs::default()
+///     .with_preferred(IpVersion::V4)
+///     .connect(("example.com", 80))
+///     .unwrap();
+/// ```
+pub struct HappyEyeballs {
+    preferred: IpVersion,
+    attempt_delay: Duration,
+    timeout: Duration,
+}
+
+impl Default for HappyEyeballs {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HappyEyeballs {
+    /// Construct a new [`HappyEyeballs`] connector
+    ///
+    /// By default this prefers IPv6, waits [`DEFAULT_ATTEMPT_DELAY`] between attempts, and gives up on each attempt
+    /// after [`DEFAULT_CONNECT_TIMEOUT`]
+    pub const fn new() -> Self {
+        Self {
+            preferred: IpVersion::V6,
+            attempt_delay: DEFAULT_ATTEMPT_DELAY,
+            timeout: DEFAULT_CONNECT_TIMEOUT,
+        }
+    }
+
+    /// Set the [`IpVersion`] that is attempted first
+    pub const fn with_preferred(self, preferred: IpVersion) -> Self {
+        Self { preferred, ..self }
+    }
+
+    /// Set the delay between starting each connection attempt
+    pub const fn with_attempt_delay(self, attempt_delay: Duration) -> Self {
+        Self {
+            attempt_delay,
+            ..self
+        }
+    }
+
+    /// Set the timeout for each individual connection attempt
+    ///
+    /// This also bounds how long the attempts that lose the race keep running in the background.
+    pub const fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Resolve the provided address, and race connections to each result
+    ///
+    /// Returns the first connection that succeeds. Connections that succeed afterwards are closed, and attempts still
+    /// in progress are left to finish or time out in the background.
+    ///
+    /// # Errors
+    /// - The address could not be resolved
+    /// - Every connection attempt failed, in which case the last error is returned
+    pub fn connect(&self, addr: impl ToSocketAddrs) -> io::Result<TcpStream> {
+        let addrs = interleave(addr.to_socket_addrs()?, self.preferred);
+
+        if addrs.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "could not resolve to any addresses",
+            ));
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let mut pending = addrs.into_iter();
+        let mut in_flight = 0usize;
+        let mut last_error = None;
+
+        loop {
+            let started = if let Some(addr) = pending.next() {
+                let tx = tx.clone();
+                let timeout = self.timeout;
+
+                thread::spawn(move || {
+                    let result = TcpStream::connect_timeout(&addr, timeout);
+
+                    // If the receiver is gone another attempt already won, so the stream is just dropped
+                    _ = tx.send(result);
+                });
+
+                in_flight += 1;
+                true
+            } else {
+                false
+            };
+
+            if in_flight == 0 {
+                break;
+            }
+
+            // Once every address has been attempted, there is nothing left to stagger
+            let result = if started {
+                match rx.recv_timeout(self.attempt_delay) {
+                    Ok(result) => result,
+                    Err(_) => continue,
+                }
+            } else {
+                match rx.recv() {
+                    Ok(result) => result,
+                    Err(_) => break,
+                }
+            };
+
+            in_flight -= 1;
+
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => last_error = Some(e),
+            }
+        }
+
+        Err(last_error.unwrap_or_else(|| io::Error::from(io::ErrorKind::NotConnected)))
+    }
+}
+
+/// Connect to the provided address using Happy Eyeballs, with the default configuration
+///
+/// # Errors
+/// - See [`HappyEyeballs::connect`]
+pub fn connect(addr: impl ToSocketAddrs) -> io::Result<TcpStream> {
+    HappyEyeballs::default().connect(addr)
+}
+
+/// Orders addresses so they alternate between IP versions, starting with the preferred version
+///
+/// The relative order of addresses within each version is kept, as that is the order the resolver sorted them in.
+fn interleave(
+    addrs: impl IntoIterator<Item = SocketAddr>,
+    preferred: IpVersion,
+) -> Vec<SocketAddr> {
+    let (first, second): (Vec<_>, Vec<_>) = addrs
+        .into_iter()
+        .partition(|addr| IpVersion::from(*addr) == preferred);
+
+    let mut first = first.into_iter();
+    let mut second = second.into_iter();
+    let mut addrs = Vec::new();
+
+    loop {
+        match (first.next(), second.next()) {
+            (None, None) => break,
+            (a, b) => addrs.extend(a.into_iter().chain(b)),
+        }
+    }
+
+    addrs
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr, TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn test_interleave() {
+        let addrs: Vec<SocketAddr> = ["[::1]:1", "[::2]:1", "[::3]:1", "1.1.1.1:1", "2.2.2.2:1"]
+            .iter()
+            .map(|addr| addr.parse().unwrap())
+            .collect();
+
+        let ordered = interleave(addrs.clone(), IpVersion::V6);
+        assert_eq!(
+            ordered,
+            [addrs[0], addrs[3], addrs[1], addrs[4], addrs[2]].to_vec()
+        );
+
+        let ordered = interleave(addrs.clone(), IpVersion::V4);
+        assert_eq!(
+            ordered,
+            [addrs[3], addrs[0], addrs[4], addrs[1], addrs[2]].to_vec()
+        );
+    }
+
+    #[test]
+    fn test_preferred_version() {
+        let v4 = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+        let mut addrs = vec![v4.local_addr().unwrap()];
+
+        let v6 = IpVersion::V6
+            .is_enabled()
+            .then(|| TcpListener::bind((Ipv6Addr::LOCALHOST, 0)).unwrap());
+        addrs.extend(v6.as_ref().map(|v6| v6.local_addr().unwrap()));
+
+        let stream = HappyEyeballs::new()
+            .with_preferred(IpVersion::V4)
+            .connect(addrs.as_slice())
+            .unwrap();
+        assert_eq!(stream.peer_addr().unwrap(), addrs[0]);
+
+        if v6.is_some() {
+            let stream = HappyEyeballs::new()
+                .with_preferred(IpVersion::V6)
+                .connect(addrs.as_slice())
+                .unwrap();
+            assert_eq!(stream.peer_addr().unwrap(), addrs[1]);
+        }
+    }
+
+    #[test]
+    fn test_fallback() {
+        let closed = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let open = TcpListener::bind((Ipv4Addr::LOCALHOST, 0)).unwrap();
+
+        let addrs = [closed, open.local_addr().unwrap()];
+        let stream = HappyEyeballs::new()
+            .with_attempt_delay(Duration::from_secs(10))
+            .connect(addrs.as_slice())
+            .unwrap();
+
+        // A refused connection starts the next attempt immediately, rather than waiting out the delay
+        assert_eq!(stream.peer_addr().unwrap(), addrs[1]);
+    }
+
+    #[test]
+    fn test_all_fail() {
+        let closed = TcpListener::bind((Ipv4Addr::LOCALHOST, 0))
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        assert!(connect(closed).is_err());
+        assert_eq!(
+            connect(&[][..] as &[SocketAddr]).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput
+        );
+    }
+}
@@ -0,0 +1,202 @@
+//! Network interface enumeration
+
+use std::{
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, UdpSocket},
+};
+
+use nix::{ifaddrs::getifaddrs, net::if_::InterfaceFlags, sys::socket::SockaddrStorage};
+
+use super::{tunnel::TunnelKind, IpNetwork, IpVersion};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// An address assigned to a network interface
+pub struct InterfaceAddress {
+    /// The assigned address
+    pub addr: IpAddr,
+    /// The prefix length of the network the address belongs to
+    pub prefix_len: u8,
+}
+
+impl InterfaceAddress {
+    #[must_use]
+    /// Gets the [`IpVersion`] of the address
+    pub fn ip_version(&self) -> IpVersion {
+        self.addr.into()
+    }
+
+    #[must_use]
+    /// Gets the address along with the network it belongs to (i.e `192.168.1.20/24`)
+    pub fn network(&self) -> IpNetwork {
+        IpNetwork::new(self.addr, self.prefix_len).unwrap_or_else(|_| IpNetwork::host(self.addr))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A network interface on the host
+pub struct Interface {
+    /// The name of the interface (i.e `eth0`)
+    pub name: String,
+    /// The index of the interface, as used by the kernel
+    pub index: u32,
+    /// Whether the interface is administratively up
+    pub up: bool,
+    /// Whether the interface is a loopback interface
+    pub loopback: bool,
+    /// Whether the interface is a point-to-point link
+    pub point_to_point: bool,
+    /// The addresses assigned to the interface
+    pub addresses: Vec<InterfaceAddress>,
+    /// The kind of tunnel, if the interface is a tunnel
+    pub tunnel: Option<TunnelKind>,
+}
+
+impl Interface {
+    /// Gets the addresses of the interface that use the provided [`IpVersion`]
+    pub fn addresses_for(&self, version: IpVersion) -> impl Iterator<Item = &InterfaceAddress> {
+        self.addresses
+            .iter()
+            .filter(move |addr| addr.ip_version() == version)
+    }
+}
+
+fn to_ip(addr: &SockaddrStorage) -> Option<IpAddr> {
+    if let Some(v4) = addr.as_sockaddr_in() {
+        Some(IpAddr::V4(v4.ip()))
+    } else {
+        addr.as_sockaddr_in6().map(|v6| IpAddr::V6(v6.ip()))
+    }
+}
+
+fn prefix_len(netmask: Option<&SockaddrStorage>) -> u8 {
+    #[allow(clippy::cast_possible_truncation)]
+    match netmask.and_then(to_ip) {
+        Some(IpAddr::V4(mask)) => u32::from(mask).count_ones() as u8,
+        Some(IpAddr::V6(mask)) => u128::from(mask).count_ones() as u8,
+        None => 0,
+    }
+}
+
+/// Lists the network interfaces on the host, along with their addresses
+///
+/// # Errors
+/// - The interface addresses could not be read
+pub fn interfaces() -> std::io::Result<Vec<Interface>> {
+    let mut interfaces: Vec<Interface> = Vec::new();
+
+    for ifaddr in getifaddrs()? {
+        let index = if let Some(index) = interfaces
+            .iter()
+            .position(|iface| iface.name == ifaddr.interface_name)
+        {
+            index
+        } else {
+            let name = ifaddr.interface_name.clone();
+
+            interfaces.push(Interface {
+                index: nix::net::if_::if_nametoindex(name.as_str()).unwrap_or_default(),
+                up: ifaddr.flags.contains(InterfaceFlags::IFF_UP),
+                loopback: ifaddr.flags.contains(InterfaceFlags::IFF_LOOPBACK),
+                point_to_point: ifaddr.flags.contains(InterfaceFlags::IFF_POINTOPOINT),
+                addresses: Vec::new(),
+                tunnel: TunnelKind::detect(&name),
+                name,
+            });
+
+            interfaces.len() - 1
+        };
+
+        if let Some(addr) = ifaddr.address.as_ref().and_then(to_ip) {
+            interfaces[index].addresses.push(InterfaceAddress {
+                addr,
+                prefix_len: prefix_len(ifaddr.netmask.as_ref()),
+            });
+        }
+    }
+
+    Ok(interfaces)
+}
+
+/// Finds a network interface by its name
+///
+/// # Errors
+/// - See [`interfaces`]
+pub fn interface_by_name(name: &str) -> std::io::Result<Option<Interface>> {
+    Ok(interfaces()?.into_iter().find(|iface| iface.name == name))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The address the host uses for outbound traffic, along with the interface it is assigned to
+pub struct PrimaryAddress {
+    /// The source address of outbound traffic
+    pub addr: IpAddr,
+    /// The interface the address is assigned to
+    pub interface: Interface,
+}
+
+/// Finds the address the host would use as the source of outbound traffic for the provided [`IpVersion`]
+///
+/// This connects a UDP socket to a public address, which asks the kernel to pick a route without sending any packets,
+/// and then finds the interface the chosen source address is assigned to.
+///
+/// # Errors
+/// - There is no route to the internet for the provided [`IpVersion`]
+/// - The interface addresses could not be read
+/// - No interface has the chosen source address assigned
+pub fn primary_address(version: IpVersion) -> io::Result<PrimaryAddress> {
+    let probe = match version {
+        IpVersion::V4 => IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+        IpVersion::V6 => IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111)),
+    };
+
+    let socket = UdpSocket::bind((version.unspecified(), 0))?;
+    socket.connect((probe, 53))?;
+    let addr = socket.local_addr()?.ip();
+
+    let interface = interfaces()?
+        .into_iter()
+        .find(|iface| iface.addresses.iter().any(|address| address.addr == addr))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("No interface has the address {addr}"),
+            )
+        })?;
+
+    Ok(PrimaryAddress { addr, interface })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_loopback() {
+        let interfaces = interfaces().unwrap();
+
+        let lo = interfaces.iter().find(|iface| iface.loopback).unwrap();
+
+        assert!(lo.up);
+        assert!(lo.tunnel.is_none());
+        assert!(lo
+            .addresses_for(IpVersion::V4)
+            .any(|addr| addr.addr.is_loopback() && addr.prefix_len == 8));
+    }
+
+    #[test]
+    fn test_primary_address() {
+        // The test environment may not have a route to the internet
+        if let Ok(primary) = primary_address(IpVersion::V4) {
+            assert!(primary.addr.is_ipv4());
+            assert!(!primary.addr.is_unspecified());
+            assert!(primary.interface.up);
+            assert!(primary
+                .interface
+                .addresses
+                .iter()
+                .any(|address| address.addr == primary.addr));
+        }
+    }
+}
@@ -0,0 +1,459 @@
+//! CIDR network blocks
+
+use std::{
+    fmt,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    str::FromStr,
+};
+
+use super::IpVersion;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when constructing or parsing an [`IpNetwork`]
+pub enum Error {
+    #[error("Invalid prefix length {prefix_len} for {version}")]
+    /// The prefix length is longer than the address
+    InvalidPrefixLength {
+        /// The invalid prefix length
+        prefix_len: u8,
+        /// The version of the address
+        version: IpVersion,
+    },
+    #[error("Invalid network: {0:?}")]
+    /// The string is not in the form `address/prefix_len`
+    InvalidFormat(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// An IP network in CIDR notation, such as `10.0.0.0/8` or `2001:db8::/32`
+///
+/// The address is kept as provided, so an interface address such as `192.168.1.20/24` can be represented.
+/// Use [`IpNetwork::network`] to get the address with the host bits cleared.
+///
+/// # Examples
+///
+/// ```
+/// # use quork::network::IpNetwork;
+/// let net: IpNetwork = "10.0.0.0/8".parse().unwrap();
+///
+/// assert!(net.contains("10.20.30.40".parse().unwrap()));
+/// assert_eq!(net.broadcast().to_string(), "10.255.255.255");
+/// ```
+pub struct IpNetwork {
+    addr: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    /// Construct a new [`IpNetwork`] from an address and prefix length
+    ///
+    /// # Errors
+    /// - The prefix length is longer than the address (32 bits for IPv4, 128 bits for IPv6)
+    pub fn new(addr: IpAddr, prefix_len: u8) -> Result<Self, Error> {
+        let version = IpVersion::from(addr);
+
+        if prefix_len > version.bits() {
+            return Err(Error::InvalidPrefixLength {
+                prefix_len,
+                version,
+            });
+        }
+
+        Ok(Self { addr, prefix_len })
+    }
+
+    #[must_use]
+    /// Construct an [`IpNetwork`] containing only the provided address
+    pub fn host(addr: IpAddr) -> Self {
+        Self {
+            addr,
+            prefix_len: IpVersion::from(addr).bits(),
+        }
+    }
+
+    #[must_use]
+    /// Gets the address, as provided when constructed
+    pub fn addr(&self) -> IpAddr {
+        self.addr
+    }
+
+    #[must_use]
+    /// Gets the prefix length
+    pub fn prefix_len(&self) -> u8 {
+        self.prefix_len
+    }
+
+    #[must_use]
+    /// Gets the [`IpVersion`] of the network
+    pub fn ip_version(&self) -> IpVersion {
+        self.addr.into()
+    }
+
+    fn bits(&self) -> u128 {
+        match self.addr {
+            IpAddr::V4(addr) => u128::from(u32::from(addr)),
+            IpAddr::V6(addr) => u128::from(addr),
+        }
+    }
+
+    fn addr_from_bits(&self, bits: u128) -> IpAddr {
+        match self.addr {
+            #[allow(clippy::cast_possible_truncation)]
+            IpAddr::V4(_) => IpAddr::V4(Ipv4Addr::from(bits as u32)),
+            IpAddr::V6(_) => IpAddr::V6(Ipv6Addr::from(bits)),
+        }
+    }
+
+    /// All bits of an address of this version set
+    fn all_bits(&self) -> u128 {
+        u128::MAX >> (128 - u32::from(self.ip_version().bits()))
+    }
+
+    fn mask_bits(&self) -> u128 {
+        let host_bits = u32::from(self.ip_version().bits() - self.prefix_len);
+
+        self.all_bits() & u128::MAX.checked_shl(host_bits).unwrap_or(0)
+    }
+
+    #[must_use]
+    /// Gets the netmask (i.e `255.255.255.0` for a `/24`)
+    pub fn netmask(&self) -> IpAddr {
+        self.addr_from_bits(self.mask_bits())
+    }
+
+    #[must_use]
+    /// Gets the hostmask, which is the inverse of the netmask (i.e `0.0.0.255` for a `/24`)
+    pub fn hostmask(&self) -> IpAddr {
+        self.addr_from_bits(self.all_bits() & !self.mask_bits())
+    }
+
+    #[must_use]
+    /// Gets the network address, which is the address with all host bits cleared
+    pub fn network(&self) -> IpAddr {
+        self.addr_from_bits(self.bits() & self.mask_bits())
+    }
+
+    #[must_use]
+    /// Gets the broadcast address, which is the address with all host bits set
+    ///
+    /// IPv6 has no broadcast, so for IPv6 networks this is the last address in the network.
+    pub fn broadcast(&self) -> IpAddr {
+        self.addr_from_bits(self.bits() | (self.all_bits() & !self.mask_bits()))
+    }
+
+    #[must_use]
+    /// Gets this network with the host bits of the address cleared (i.e `10.1.2.3/8` becomes `10.0.0.0/8`)
+    pub fn trunc(&self) -> Self {
+        Self {
+            addr: self.network(),
+            prefix_len: self.prefix_len,
+        }
+    }
+
+    #[must_use]
+    /// Checks if the provided address is within the network
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        IpVersion::from(addr) == self.ip_version()
+            && (self.bits() & self.mask_bits()) == (Self::host(addr).bits() & self.mask_bits())
+    }
+
+    #[must_use]
+    /// Checks if every address in the provided network is within this network
+    pub fn contains_network(&self, other: &IpNetwork) -> bool {
+        other.prefix_len >= self.prefix_len && self.contains(other.addr)
+    }
+
+    #[must_use]
+    /// Checks if this network shares any addresses with the provided network
+    pub fn overlaps(&self, other: &IpNetwork) -> bool {
+        self.contains_network(other) || other.contains_network(self)
+    }
+
+    #[must_use]
+    /// Gets the number of addresses in the network
+    ///
+    /// Returns [`None`] for an IPv6 `/0`, which has too many addresses to represent.
+    pub fn size(&self) -> Option<u128> {
+        1u128.checked_shl(u32::from(self.ip_version().bits() - self.prefix_len))
+    }
+
+    #[must_use]
+    /// Iterates over the usable host addresses in the network
+    ///
+    /// For IPv4 the network and broadcast addresses are excluded, except for `/31` and `/32` networks.
+    /// For IPv6 the network address (the subnet-router anycast address) is excluded, except for `/127` and `/128` networks.
+    pub fn hosts(&self) -> Hosts {
+        let first = self.bits() & self.mask_bits();
+        let last = self.bits() | (self.all_bits() & !self.mask_bits());
+        let host_bits = self.ip_version().bits() - self.prefix_len;
+
+        let (first, last) = match self.ip_version() {
+            IpVersion::V4 if host_bits >= 2 => (first + 1, last - 1),
+            IpVersion::V6 if host_bits >= 2 => (first + 1, last),
+            _ => (first, last),
+        };
+
+        Hosts {
+            network: *self,
+            next: Some(first),
+            last,
+        }
+    }
+
+    #[must_use]
+    /// Gets the network one bit shorter that contains this network (i.e `10.0.0.0/8` for `10.0.0.0/9`)
+    ///
+    /// Returns [`None`] if the prefix length is already `0`
+    pub fn supernet(&self) -> Option<Self> {
+        let prefix_len = self.prefix_len.checked_sub(1)?;
+
+        Some(
+            Self {
+                addr: self.addr,
+                prefix_len,
+            }
+            .trunc(),
+        )
+    }
+
+    /// Iterates over the subnets of this network with the provided prefix length
+    ///
+    /// # Errors
+    /// - The new prefix length is shorter than the current prefix length, or longer than the address
+    pub fn subnets(&self, prefix_len: u8) -> Result<Subnets, Error> {
+        if prefix_len < self.prefix_len || prefix_len > self.ip_version().bits() {
+            return Err(Error::InvalidPrefixLength {
+                prefix_len,
+                version: self.ip_version(),
+            });
+        }
+
+        let base = self.trunc();
+
+        Ok(Subnets {
+            next: Some(base.bits()),
+            last: base.broadcast_bits(),
+            step: 1u128
+                .checked_shl(u32::from(self.ip_version().bits() - prefix_len))
+                .unwrap_or(0),
+            template: Self {
+                addr: base.addr,
+                prefix_len,
+            },
+        })
+    }
+
+    #[must_use]
+    /// Splits the network into its two halves
+    ///
+    /// Returns [`None`] if the network is a single address
+    pub fn split(&self) -> Option<(Self, Self)> {
+        let mut subnets = self.subnets(self.prefix_len.checked_add(1)?).ok()?;
+
+        Some((subnets.next()?, subnets.next()?))
+    }
+
+    fn broadcast_bits(&self) -> u128 {
+        Self::host(self.broadcast()).bits()
+    }
+}
+
+impl From<IpAddr> for IpNetwork {
+    fn from(addr: IpAddr) -> Self {
+        Self::host(addr)
+    }
+}
+
+impl fmt::Display for IpNetwork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl FromStr for IpNetwork {
+    type Err = Error;
+
+    /// Parses a network such as `10.0.0.0/8`
+    ///
+    /// A bare address is parsed as a network containing only that address
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || Error::InvalidFormat(s.to_string());
+
+        match s.split_once('/') {
+            Some((addr, prefix_len)) => Self::new(
+                addr.parse().map_err(|_| invalid())?,
+                prefix_len.parse().map_err(|_| invalid())?,
+            ),
+            None => Ok(Self::host(s.parse().map_err(|_| invalid())?)),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for IpNetwork {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for IpNetwork {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An iterator over the host addresses of an [`IpNetwork`]
+///
+/// Created by [`IpNetwork::hosts`]
+pub struct Hosts {
+    network: IpNetwork,
+    next: Option<u128>,
+    last: u128,
+}
+
+impl Iterator for Hosts {
+    type Item = IpAddr;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.filter(|next| *next <= self.last)?;
+        self.next = current.checked_add(1);
+
+        Some(self.network.addr_from_bits(current))
+    }
+}
+
+#[derive(Debug, Clone)]
+/// An iterator over the subnets of an [`IpNetwork`]
+///
+/// Created by [`IpNetwork::subnets`]
+pub struct Subnets {
+    template: IpNetwork,
+    next: Option<u128>,
+    last: u128,
+    step: u128,
+}
+
+impl Iterator for Subnets {
+    type Item = IpNetwork;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let current = self.next.filter(|next| *next <= self.last)?;
+
+        // A step of 0 means the subnet is the whole address space, so there is only one
+        self.next = if self.step == 0 {
+            None
+        } else {
+            current.checked_add(self.step)
+        };
+
+        Some(IpNetwork {
+            addr: self.template.addr_from_bits(current),
+            prefix_len: self.template.prefix_len,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn net(s: &str) -> IpNetwork {
+        s.parse().unwrap()
+    }
+
+    fn ip(s: &str) -> IpAddr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_parse_display() {
+        assert_eq!(net("10.0.0.0/8").to_string(), "10.0.0.0/8");
+        assert_eq!(net("2001:db8::/32").ip_version(), IpVersion::V6);
+        assert_eq!(net("192.168.1.1").prefix_len(), 32);
+
+        assert!("10.0.0.0/33".parse::<IpNetwork>().is_err());
+        assert!("10.0.0/8".parse::<IpNetwork>().is_err());
+    }
+
+    #[test]
+    fn test_masks() {
+        let lan = net("192.168.1.20/24");
+
+        assert_eq!(lan.netmask(), ip("255.255.255.0"));
+        assert_eq!(lan.hostmask(), ip("0.0.0.255"));
+        assert_eq!(lan.network(), ip("192.168.1.0"));
+        assert_eq!(lan.broadcast(), ip("192.168.1.255"));
+        assert_eq!(lan.trunc(), net("192.168.1.0/24"));
+
+        assert_eq!(net("0.0.0.0/0").netmask(), ip("0.0.0.0"));
+        assert_eq!(
+            net("::/0").broadcast(),
+            ip("ffff:ffff:ffff:ffff:ffff:ffff:ffff:ffff")
+        );
+        assert_eq!(net("fe80::1/64").network(), ip("fe80::"));
+    }
+
+    #[test]
+    fn test_contains_overlaps() {
+        let private = net("10.0.0.0/8");
+
+        assert!(private.contains(ip("10.255.0.1")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(!private.contains(ip("::a00:1")));
+
+        assert!(private.contains_network(&net("10.1.0.0/16")));
+        assert!(!net("10.1.0.0/16").contains_network(&private));
+
+        assert!(private.overlaps(&net("10.1.0.0/16")));
+        assert!(net("10.1.0.0/16").overlaps(&private));
+        assert!(!private.overlaps(&net("172.16.0.0/12")));
+        assert!(net("0.0.0.0/0").contains(ip("1.1.1.1")));
+    }
+
+    #[test]
+    fn test_hosts() {
+        let hosts: Vec<_> = net("192.168.0.0/30").hosts().collect();
+        assert_eq!(hosts, [ip("192.168.0.1"), ip("192.168.0.2")]);
+
+        assert_eq!(net("10.0.0.0/31").hosts().count(), 2);
+        assert_eq!(
+            net("10.0.0.1/32").hosts().collect::<Vec<_>>(),
+            [ip("10.0.0.1")]
+        );
+        assert_eq!(net("255.255.255.254/31").hosts().count(), 2);
+
+        let hosts: Vec<_> = net("2001:db8::/126").hosts().collect();
+        assert_eq!(hosts.len(), 3);
+        assert_eq!(hosts[0], ip("2001:db8::1"));
+
+        assert_eq!(net("10.0.0.0/8").size(), Some(1 << 24));
+        assert_eq!(net("::/0").size(), None);
+    }
+
+    #[test]
+    fn test_supernet_subnets() {
+        assert_eq!(net("10.128.0.0/9").supernet(), Some(net("10.0.0.0/8")));
+        assert_eq!(net("0.0.0.0/0").supernet(), None);
+
+        let subnets: Vec<_> = net("10.0.0.0/8").subnets(10).unwrap().collect();
+        assert_eq!(
+            subnets,
+            [
+                net("10.0.0.0/10"),
+                net("10.64.0.0/10"),
+                net("10.128.0.0/10"),
+                net("10.192.0.0/10"),
+            ]
+        );
+
+        assert_eq!(net("::/0").split(), Some((net("::/1"), net("8000::/1"))));
+        assert_eq!(net("0.0.0.0/0").subnets(0).unwrap().count(), 1);
+        assert_eq!(net("255.255.255.0/24").subnets(32).unwrap().count(), 256);
+        assert!(net("10.0.0.0/8").subnets(4).is_err());
+        assert_eq!(net("10.0.0.1/32").split(), None);
+    }
+}
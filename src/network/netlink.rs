@@ -0,0 +1,115 @@
+//! Minimal `NETLINK_ROUTE` dump requests
+
+use std::{io, os::fd::AsRawFd};
+
+use nix::sys::socket::{
+    bind, recv, send, socket, AddressFamily, MsgFlags, NetlinkAddr, SockFlag, SockProtocol,
+    SockType,
+};
+
+const NLMSG_ERROR: u16 = 2;
+const NLMSG_DONE: u16 = 3;
+
+const NLM_F_REQUEST: u16 = 0x1;
+const NLM_F_DUMP: u16 = 0x300;
+
+/// The length of `nlmsghdr`
+const HEADER_LEN: usize = 16;
+
+/// The length of both `rtmsg` and `fib_rule_hdr`, which start with the address family
+pub(super) const FAMILY_HEADER_LEN: usize = 12;
+
+/// Rounds a length up to the 4 byte alignment netlink uses
+const fn align(len: usize) -> usize {
+    (len + 3) & !3
+}
+
+/// Dumps every object of a kind, such as every route or every rule, for the provided address family
+///
+/// Returns the payload of each message, starting with its family header.
+pub(super) fn dump(msg_type: u16, family: u8) -> io::Result<Vec<Vec<u8>>> {
+    let fd = socket(
+        AddressFamily::Netlink,
+        SockType::Raw,
+        SockFlag::SOCK_CLOEXEC,
+        SockProtocol::NetlinkRoute,
+    )?;
+    bind(fd.as_raw_fd(), &NetlinkAddr::new(0, 0))?;
+
+    #[allow(clippy::cast_possible_truncation)]
+    let len = (HEADER_LEN + FAMILY_HEADER_LEN) as u32;
+
+    let mut request = Vec::with_capacity(HEADER_LEN + FAMILY_HEADER_LEN);
+    request.extend_from_slice(&len.to_ne_bytes());
+    request.extend_from_slice(&msg_type.to_ne_bytes());
+    request.extend_from_slice(&(NLM_F_REQUEST | NLM_F_DUMP).to_ne_bytes());
+    // Sequence number and port id, which the kernel fills in
+    request.extend_from_slice(&[0; 8]);
+    request.push(family);
+    request.extend_from_slice(&[0; FAMILY_HEADER_LEN - 1]);
+
+    send(fd.as_raw_fd(), &request, MsgFlags::empty())?;
+
+    let mut messages = Vec::new();
+    let mut buf = vec![0u8; 32 * 1024];
+
+    loop {
+        let read = recv(fd.as_raw_fd(), &mut buf, MsgFlags::empty())?;
+        let mut rest = &buf[..read];
+
+        while rest.len() >= HEADER_LEN {
+            let len = u32::from_ne_bytes(rest[0..4].try_into().unwrap_or_default()) as usize;
+            let kind = u16::from_ne_bytes([rest[4], rest[5]]);
+
+            if len < HEADER_LEN || len > rest.len() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "Truncated netlink message",
+                ));
+            }
+
+            let payload = &rest[HEADER_LEN..len];
+            match kind {
+                NLMSG_DONE => return Ok(messages),
+                NLMSG_ERROR => {
+                    let code = payload.get(..4).map_or(0, |code| {
+                        i32::from_ne_bytes(code.try_into().unwrap_or_default())
+                    });
+
+                    // An error code of zero is an acknowledgement
+                    if code != 0 {
+                        return Err(io::Error::from_raw_os_error(-code));
+                    }
+                }
+                // Each `RTM_GET*` request is answered with the matching `RTM_NEW*` messages
+                _ if kind == msg_type - 2 => messages.push(payload.to_vec()),
+                _ => {}
+            }
+
+            rest = rest.get(align(len)..).unwrap_or_default();
+        }
+    }
+}
+
+/// Iterates over the attributes following a family header, as `(type, value)` pairs
+pub(super) fn attributes(payload: &[u8]) -> impl Iterator<Item = (u16, &[u8])> {
+    // The type includes flags in its top bits
+    const TYPE_MASK: u16 = 0x3fff;
+
+    let mut rest = payload.get(FAMILY_HEADER_LEN..).unwrap_or_default();
+
+    std::iter::from_fn(move || {
+        let len = usize::from(u16::from_ne_bytes([*rest.first()?, *rest.get(1)?]));
+        let kind = u16::from_ne_bytes([*rest.get(2)?, *rest.get(3)?]) & TYPE_MASK;
+
+        let value = rest.get(4..len)?;
+        rest = rest.get(align(len)..).unwrap_or_default();
+
+        Some((kind, value))
+    })
+}
+
+/// Reads an attribute value as a native-endian `u32`
+pub(super) fn u32_value(value: &[u8]) -> Option<u32> {
+    Some(u32::from_ne_bytes(value.get(..4)?.try_into().ok()?))
+}
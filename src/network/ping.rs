@@ -0,0 +1,455 @@
+//! ICMP echo (ping)
+//!
+//! Where the host allows it, pings are sent over unprivileged `SOCK_DGRAM` ICMP sockets, which on Linux is controlled by
+//! the `net.ipv4.ping_group_range` sysctl. Otherwise raw sockets are used, which require `CAP_NET_RAW` (usually root).
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr, UdpSocket},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    errno::Errno,
+    sys::socket::{socket, AddressFamily, SockFlag, SockProtocol, SockType},
+};
+
+use super::IpVersion;
+
+const ICMP_ECHO_REQUEST: u8 = 8;
+const ICMP_ECHO_REPLY: u8 = 0;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+const HEADER_LEN: usize = 8;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when sending pings
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The socket could not be created, or a ping could not be sent
+    Io(#[from] io::Error),
+    #[error("Neither unprivileged nor raw ICMP sockets are permitted")]
+    /// Neither unprivileged nor raw ICMP sockets are available
+    PermissionDenied,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// The kind of socket pings were sent with
+pub enum SocketKind {
+    /// An unprivileged `SOCK_DGRAM` ICMP socket
+    Datagram,
+    /// A raw ICMP socket
+    Raw,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The results of pinging an address
+pub struct PingStats {
+    /// The address that was pinged
+    pub addr: IpAddr,
+    /// The kind of socket the pings were sent with
+    pub kind: SocketKind,
+    /// The number of echo requests sent
+    pub transmitted: u16,
+    /// The round trip time of each echo reply received
+    pub rtts: Vec<Duration>,
+}
+
+impl PingStats {
+    #[must_use]
+    /// The number of echo replies received
+    pub fn received(&self) -> usize {
+        self.rtts.len()
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    /// The percentage of echo requests that did not receive a reply
+    pub fn loss(&self) -> f64 {
+        if self.transmitted == 0 {
+            return 0.0;
+        }
+
+        100.0 - (self.received() as f64 / f64::from(self.transmitted) * 100.0)
+    }
+
+    #[must_use]
+    /// The shortest round trip time
+    pub fn min(&self) -> Option<Duration> {
+        self.rtts.iter().min().copied()
+    }
+
+    #[must_use]
+    /// The longest round trip time
+    pub fn max(&self) -> Option<Duration> {
+        self.rtts.iter().max().copied()
+    }
+
+    #[must_use]
+    /// The mean round trip time
+    pub fn avg(&self) -> Option<Duration> {
+        let count = u32::try_from(self.rtts.len())
+            .ok()
+            .filter(|count| *count > 0)?;
+
+        Some(self.rtts.iter().sum::<Duration>() / count)
+    }
+
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    /// The standard deviation of the round trip times
+    pub fn stddev(&self) -> Option<Duration> {
+        let avg = self.avg()?.as_secs_f64();
+        let variance = self
+            .rtts
+            .iter()
+            .map(|rtt| (rtt.as_secs_f64() - avg).powi(2))
+            .sum::<f64>()
+            / self.rtts.len() as f64;
+
+        Some(Duration::from_secs_f64(variance.sqrt()))
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[must_use]
+/// A configurable ping
+///
+/// # Examples
+///
+/// ```no_run
+/// # use quork::network::ping::Ping;
+/// let stats = Ping::default().with_count(3).run("127.0.0.1".parse().unwrap()).unwrap();
+///
+/// println!("{}/{} replies, avg {:?}", stats.received(), stats.transmitted, stats.avg());
+/// ```
+pub struct Ping {
+    count: u16,
+    interval: Duration,
+    timeout: Duration,
+    payload_len: usize,
+}
+
+impl Default for Ping {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Ping {
+    /// Construct a new [`Ping`]
+    ///
+    /// By default this sends 4 echo requests with a 56 byte payload, one second apart, and waits up to one second for
+    /// each reply
+    pub const fn new() -> Self {
+        Self {
+            count: 4,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(1),
+            payload_len: 56,
+        }
+    }
+
+    /// Set the number of echo requests to send
+    pub const fn with_count(self, count: u16) -> Self {
+        Self { count, ..self }
+    }
+
+    /// Set the delay between sending each echo request
+    pub const fn with_interval(self, interval: Duration) -> Self {
+        Self { interval, ..self }
+    }
+
+    /// Set how long to wait for each echo reply
+    pub const fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Set the size of the payload of each echo request
+    pub const fn with_payload_len(self, payload_len: usize) -> Self {
+        Self {
+            payload_len,
+            ..self
+        }
+    }
+
+    /// Ping the provided address
+    ///
+    /// Requests that do not receive a reply before the timeout are counted as lost, rather than returning an error.
+    ///
+    /// # Errors
+    /// - Neither unprivileged nor raw ICMP sockets are permitted
+    /// - The socket could not be created
+    /// - An echo request could not be sent
+    pub fn run(&self, addr: IpAddr) -> Result<PingStats, Error> {
+        let version = IpVersion::from(addr);
+        let (socket, kind) = open(version)?;
+        let target = SocketAddr::new(addr, 0);
+
+        #[allow(clippy::cast_possible_truncation)]
+        let ident = std::process::id() as u16;
+        let payload: Vec<u8> = (0..=u8::MAX).cycle().take(self.payload_len).collect();
+
+        let mut stats = PingStats {
+            addr,
+            kind,
+            transmitted: 0,
+            rtts: Vec::new(),
+        };
+
+        for seq in 0..self.count {
+            if seq > 0 {
+                std::thread::sleep(self.interval);
+            }
+
+            let request = echo_request(version, ident, seq, &payload);
+            let sent = Instant::now();
+            socket.send_to(&request, target)?;
+            stats.transmitted += 1;
+
+            if wait_for_reply(&socket, kind, version, ident, seq, sent + self.timeout)? {
+                stats.rtts.push(sent.elapsed());
+            }
+        }
+
+        Ok(stats)
+    }
+}
+
+/// Ping the provided address with the default configuration
+///
+/// # Errors
+/// - See [`Ping::run`]
+pub fn ping(addr: IpAddr) -> Result<PingStats, Error> {
+    Ping::default().run(addr)
+}
+
+#[must_use]
+/// Checks if the process is permitted to create unprivileged ICMP sockets
+///
+/// On Linux this checks if the effective group, or any supplementary group, is within `net.ipv4.ping_group_range`.
+pub fn unprivileged_allowed() -> bool {
+    #[cfg(target_os = "linux")]
+    {
+        let Some((low, high)) = std::fs::read_to_string("/proc/sys/net/ipv4/ping_group_range")
+            .ok()
+            .and_then(|range| parse_group_range(&range))
+        else {
+            return false;
+        };
+
+        let in_range = |gid: nix::unistd::Gid| (low..=high).contains(&gid.as_raw());
+
+        in_range(nix::unistd::getegid())
+            || nix::unistd::getgroups().is_ok_and(|groups| groups.into_iter().any(in_range))
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    true
+}
+
+#[cfg(target_os = "linux")]
+fn parse_group_range(range: &str) -> Option<(u32, u32)> {
+    let mut parts = range.split_whitespace().map(str::parse);
+
+    match (parts.next()?, parts.next()?) {
+        (Ok(low), Ok(high)) => Some((low, high)),
+        _ => None,
+    }
+}
+
+fn open(version: IpVersion) -> Result<(UdpSocket, SocketKind), Error> {
+    let (family, protocol) = match version {
+        IpVersion::V4 => (AddressFamily::Inet, SockProtocol::Icmp),
+        IpVersion::V6 => (AddressFamily::Inet6, SockProtocol::IcmpV6),
+    };
+
+    if unprivileged_allowed() {
+        if let Ok(fd) = socket(family, SockType::Datagram, SockFlag::empty(), protocol) {
+            // ICMP sockets behave like any other datagram socket, so the standard library can drive them
+            return Ok((UdpSocket::from(fd), SocketKind::Datagram));
+        }
+    }
+
+    // Raw sockets need `CAP_NET_RAW` rather than root itself, so just try to open one
+    let fd = socket(family, SockType::Raw, SockFlag::empty(), protocol).map_err(|e| match e {
+        Errno::EPERM | Errno::EACCES => Error::PermissionDenied,
+        e => Error::Io(e.into()),
+    })?;
+
+    Ok((UdpSocket::from(fd), SocketKind::Raw))
+}
+
+fn echo_request(version: IpVersion, ident: u16, seq: u16, payload: &[u8]) -> Vec<u8> {
+    let kind = match version {
+        IpVersion::V4 => ICMP_ECHO_REQUEST,
+        IpVersion::V6 => ICMPV6_ECHO_REQUEST,
+    };
+
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(payload);
+
+    // The kernel always fills in the checksum for ICMPv6, as it covers the IPv6 pseudo-header
+    if version == IpVersion::V4 {
+        let checksum = checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    packet
+}
+
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|chunk| u32::from(u16::from_be_bytes([chunk[0], *chunk.get(1).unwrap_or(&0)])))
+        .sum();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    !(sum as u16)
+}
+
+fn wait_for_reply(
+    socket: &UdpSocket,
+    kind: SocketKind,
+    version: IpVersion,
+    ident: u16,
+    seq: u16,
+    deadline: Instant,
+) -> io::Result<bool> {
+    let mut buf = [0u8; 2048];
+
+    loop {
+        let Some(remaining) = deadline.checked_duration_since(Instant::now()) else {
+            return Ok(false);
+        };
+        if remaining.is_zero() {
+            return Ok(false);
+        }
+
+        socket.set_read_timeout(Some(remaining))?;
+
+        let len = match socket.recv(&mut buf) {
+            Ok(len) => len,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        };
+
+        if is_reply(&buf[..len], kind, version, ident, seq) {
+            return Ok(true);
+        }
+    }
+}
+
+fn is_reply(packet: &[u8], kind: SocketKind, version: IpVersion, ident: u16, seq: u16) -> bool {
+    // Raw IPv4 sockets receive the IP header as well
+    let packet = match (kind, version) {
+        (SocketKind::Raw, IpVersion::V4) => {
+            let Some(header_len) = packet.first().map(|b| usize::from(b & 0x0f) * 4) else {
+                return false;
+            };
+
+            packet.get(header_len..).unwrap_or_default()
+        }
+        _ => packet,
+    };
+
+    if packet.len() < HEADER_LEN {
+        return false;
+    }
+
+    let reply = match version {
+        IpVersion::V4 => ICMP_ECHO_REPLY,
+        IpVersion::V6 => ICMPV6_ECHO_REPLY,
+    };
+
+    // Unprivileged sockets have their identifier rewritten by the kernel, and only receive their own replies
+    let ident_matches =
+        kind == SocketKind::Datagram || u16::from_be_bytes([packet[4], packet[5]]) == ident;
+
+    packet[0] == reply && ident_matches && u16::from_be_bytes([packet[6], packet[7]]) == seq
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn test_echo_request() {
+        let packet = echo_request(IpVersion::V4, 0x1234, 7, &[1, 2, 3]);
+
+        assert_eq!(&packet[..2], &[ICMP_ECHO_REQUEST, 0]);
+        assert_eq!(&packet[4..], &[0x12, 0x34, 0, 7, 1, 2, 3]);
+        // A packet including its own checksum sums to zero
+        assert_eq!(checksum(&packet), 0);
+
+        let mut reply = packet.clone();
+        reply[0] = ICMP_ECHO_REPLY;
+        assert!(is_reply(&reply, SocketKind::Datagram, IpVersion::V4, 0, 7));
+        assert!(!is_reply(&reply, SocketKind::Datagram, IpVersion::V6, 0, 7));
+        assert!(!is_reply(&reply, SocketKind::Datagram, IpVersion::V4, 0, 8));
+        assert!(!is_reply(
+            &packet,
+            SocketKind::Datagram,
+            IpVersion::V4,
+            0,
+            7
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_parse_group_range() {
+        assert_eq!(parse_group_range("1\t0\n"), Some((1, 0)));
+        assert_eq!(
+            parse_group_range("0\t2147483647\n"),
+            Some((0, 2_147_483_647))
+        );
+        assert_eq!(parse_group_range("nope"), None);
+    }
+
+    #[test]
+    fn test_ping_loopback() {
+        let ping = Ping::new()
+            .with_count(3)
+            .with_interval(Duration::from_millis(10));
+
+        let mut addrs = vec![IpAddr::V4(Ipv4Addr::LOCALHOST)];
+        if IpVersion::V6.is_enabled() {
+            addrs.push(IpAddr::V6(Ipv6Addr::LOCALHOST));
+        }
+
+        for addr in addrs {
+            let stats = match ping.run(addr) {
+                Ok(stats) => stats,
+                Err(Error::PermissionDenied) => {
+                    eprintln!(
+                        "Skipping test_ping_loopback: this process may not open ICMP sockets"
+                    );
+                    return;
+                }
+                Err(e) => panic!("Failed to ping {addr}: {e}"),
+            };
+
+            assert_eq!(stats.transmitted, 3);
+            assert_eq!(stats.received(), 3);
+            assert!(stats.loss().abs() < f64::EPSILON);
+            assert!(stats.min() <= stats.avg() && stats.avg() <= stats.max());
+            assert!(stats.stddev().is_some());
+        }
+    }
+}
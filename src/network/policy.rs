@@ -0,0 +1,470 @@
+//! Policy routing
+//!
+//! Linux can route traffic with more than the main routing table, using rules (`ip rule`) which choose a table by the
+//! traffic's firewall mark, destination and more. VPNs such as `wg-quick` rely on this, so their routes never appear
+//! in the main table read by [`super::routes`].
+//!
+//! The rules, and the routes in every table, are read over netlink.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+};
+
+use nix::net::if_::if_nameindex;
+
+use super::{
+    netlink,
+    route::{best_route, Route},
+    IpNetwork, IpVersion,
+};
+
+const RTM_GETROUTE: u16 = 26;
+const RTM_GETRULE: u16 = 34;
+
+const RTA_DST: u16 = 1;
+const RTA_OIF: u16 = 4;
+const RTA_GATEWAY: u16 = 5;
+const RTA_PRIORITY: u16 = 6;
+const RTA_MULTIPATH: u16 = 9;
+const RTA_TABLE: u16 = 15;
+
+const RTN_UNICAST: u8 = 1;
+const RTM_F_CLONED: u32 = 0x200;
+
+const FRA_DST: u16 = 1;
+const FRA_SRC: u16 = 2;
+const FRA_IIFNAME: u16 = 3;
+const FRA_PRIORITY: u16 = 6;
+const FRA_FWMARK: u16 = 10;
+const FRA_SUPPRESS_PREFIXLEN: u16 = 14;
+const FRA_TABLE: u16 = 15;
+const FRA_FWMASK: u16 = 16;
+const FRA_OIFNAME: u16 = 17;
+
+const FR_ACT_TO_TBL: u8 = 1;
+const FR_ACT_BLACKHOLE: u8 = 6;
+const FR_ACT_UNREACHABLE: u8 = 7;
+const FR_ACT_PROHIBIT: u8 = 8;
+const FIB_RULE_INVERT: u32 = 0x2;
+
+/// The main routing table, which is the only table most hosts use
+pub const MAIN_TABLE: u32 = 254;
+
+/// The local routing table, which holds routes to the host's own addresses
+pub const LOCAL_TABLE: u32 = 255;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// What a rule does with the traffic it matches
+pub enum RuleAction {
+    /// Look up a route in the table, moving on to the next rule if there is none
+    Lookup(u32),
+    /// Drop the traffic (i.e `blackhole`, `unreachable` or `prohibit`)
+    Reject,
+    /// Any other action, such as `goto` or `nop`, which is treated as moving on to the next rule
+    Other,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A routing policy rule
+pub struct Rule {
+    /// The [`IpVersion`] the rule applies to
+    pub version: IpVersion,
+    /// The priority of the rule. Lower priorities are checked first
+    pub priority: u32,
+    /// The source network the rule matches, if any
+    pub source: Option<IpNetwork>,
+    /// The destination network the rule matches, if any
+    pub destination: Option<IpNetwork>,
+    /// The firewall mark the rule matches, after masking with [`Rule::fwmask`]
+    pub fwmark: u32,
+    /// The mask applied to the firewall mark. A mask of zero matches any mark
+    pub fwmask: u32,
+    /// The name of the input interface the rule matches, if any
+    pub input_interface: Option<String>,
+    /// The name of the output interface the rule matches, if any
+    pub output_interface: Option<String>,
+    /// Whether the rule matches traffic that does not match its selectors (i.e `not fwmark 0xca6c`)
+    pub invert: bool,
+    /// Routes found with a prefix length up to this are ignored (i.e `suppress_prefixlength 0` ignores default routes)
+    pub suppress_prefix_len: Option<u8>,
+    /// What the rule does with the traffic it matches
+    pub action: RuleAction,
+}
+
+impl Rule {
+    #[must_use]
+    /// Checks if the rule matches traffic sent from this host to the provided address, with the provided mark
+    ///
+    /// The source address of the traffic is not known, so rules that select a source never match.
+    /// Locally generated traffic has the loopback interface as its input interface.
+    pub fn matches(&self, addr: IpAddr, fwmark: u32) -> bool {
+        let matched = self.source.is_none()
+            && self
+                .destination
+                .map_or(true, |destination| destination.contains(addr))
+            && (fwmark ^ self.fwmark) & self.fwmask == 0
+            && self
+                .input_interface
+                .as_deref()
+                .map_or(true, |name| name == "lo")
+            && self.output_interface.is_none();
+
+        matched != self.invert
+    }
+
+    fn parse(payload: &[u8]) -> Option<Self> {
+        let version = family_version(*payload.first()?)?;
+        let (dst_len, src_len, header_table, action) = (
+            *payload.get(1)?,
+            *payload.get(2)?,
+            *payload.get(4)?,
+            *payload.get(7)?,
+        );
+        let flags = netlink::u32_value(payload.get(8..)?)?;
+
+        let mut rule = Self {
+            version,
+            priority: 0,
+            source: None,
+            destination: None,
+            fwmark: 0,
+            fwmask: 0,
+            input_interface: None,
+            output_interface: None,
+            invert: flags & FIB_RULE_INVERT != 0,
+            suppress_prefix_len: None,
+            action: RuleAction::Other,
+        };
+        let mut table = u32::from(header_table);
+        let mut has_fwmask = false;
+
+        for (kind, value) in netlink::attributes(payload) {
+            match kind {
+                FRA_DST => rule.destination = network(value, dst_len),
+                FRA_SRC => rule.source = network(value, src_len),
+                FRA_IIFNAME => rule.input_interface = Some(interface_name(value)),
+                FRA_OIFNAME => rule.output_interface = Some(interface_name(value)),
+                FRA_PRIORITY => rule.priority = netlink::u32_value(value)?,
+                FRA_FWMARK => rule.fwmark = netlink::u32_value(value)?,
+                FRA_FWMASK => {
+                    rule.fwmask = netlink::u32_value(value)?;
+                    has_fwmask = true;
+                }
+                // The kernel reports no suppression as -1
+                FRA_SUPPRESS_PREFIXLEN => {
+                    rule.suppress_prefix_len = u8::try_from(netlink::u32_value(value)?).ok();
+                }
+                FRA_TABLE => table = netlink::u32_value(value)?,
+                _ => {}
+            }
+        }
+
+        // A mark without a mask matches the whole mark
+        if rule.fwmark != 0 && !has_fwmask {
+            rule.fwmask = u32::MAX;
+        }
+
+        rule.action = match action {
+            FR_ACT_TO_TBL => RuleAction::Lookup(table),
+            FR_ACT_BLACKHOLE | FR_ACT_UNREACHABLE | FR_ACT_PROHIBIT => RuleAction::Reject,
+            _ => RuleAction::Other,
+        };
+
+        Some(rule)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The routing policy rules, along with the routes in every table
+///
+/// # Examples
+///
+/// ```no_run
+/// # use quork::network::policy::RoutingPolicy;
+/// let policy = RoutingPolicy::get().unwrap();
+///
+/// if let Some(route) = policy.route_to("1.1.1.1".parse().unwrap(), 0) {
+///     println!("Internet traffic leaves through {}", route.interface);
+/// }
+/// ```
+pub struct RoutingPolicy {
+    /// The rules, in the order they are checked
+    pub rules: Vec<Rule>,
+    /// The unicast routes in each table, by table id
+    pub tables: BTreeMap<u32, Vec<Route>>,
+}
+
+impl From<Vec<Route>> for RoutingPolicy {
+    /// Creates a policy with only the provided routes in the main table, and no rules
+    fn from(routes: Vec<Route>) -> Self {
+        Self {
+            rules: Vec::new(),
+            tables: BTreeMap::from([(MAIN_TABLE, routes)]),
+        }
+    }
+}
+
+impl RoutingPolicy {
+    /// Reads the rules and the routes in every table, for both IPv4 and IPv6
+    ///
+    /// # Errors
+    /// - The netlink socket could not be opened, or a request failed
+    pub fn get() -> io::Result<Self> {
+        let names: HashMap<u32, String> = if_nameindex()?
+            .iter()
+            .map(|iface| (iface.index(), iface.name().to_string_lossy().into_owned()))
+            .collect();
+
+        let mut policy = Self::default();
+
+        for family in [libc_family(IpVersion::V4), libc_family(IpVersion::V6)] {
+            for payload in netlink::dump(RTM_GETRULE, family)? {
+                policy.rules.extend(Rule::parse(&payload));
+            }
+
+            for payload in netlink::dump(RTM_GETROUTE, family)? {
+                if let Some((table, route)) = parse_route(&payload, &names) {
+                    policy.tables.entry(table).or_default().push(route);
+                }
+            }
+        }
+
+        policy.rules.sort_by_key(|rule| rule.priority);
+
+        Ok(policy)
+    }
+
+    #[must_use]
+    /// Gets the routes in the provided table
+    pub fn table(&self, table: u32) -> &[Route] {
+        self.tables.get(&table).map_or(&[], Vec::as_slice)
+    }
+
+    #[must_use]
+    /// Finds the route the kernel would use to reach the provided address, for traffic with the provided mark
+    ///
+    /// The rules are checked in order, as the kernel does. If there are no rules for the address' [`IpVersion`],
+    /// only the main table is used.
+    pub fn route_to(&self, addr: IpAddr, fwmark: u32) -> Option<Route> {
+        let version = IpVersion::from(addr);
+        let mut rules = self
+            .rules
+            .iter()
+            .filter(|rule| rule.version == version)
+            .peekable();
+
+        if rules.peek().is_none() {
+            return best_route(self.table(MAIN_TABLE).to_vec(), addr);
+        }
+
+        for rule in rules.filter(|rule| rule.matches(addr, fwmark)) {
+            match rule.action {
+                RuleAction::Lookup(table) => {
+                    let route = best_route(self.table(table).to_vec(), addr).filter(|route| {
+                        rule.suppress_prefix_len
+                            .map_or(true, |len| route.destination.prefix_len() > len)
+                    });
+
+                    if route.is_some() {
+                        return route;
+                    }
+                }
+                RuleAction::Reject => return None,
+                RuleAction::Other => {}
+            }
+        }
+
+        None
+    }
+}
+
+fn libc_family(version: IpVersion) -> u8 {
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    match version {
+        IpVersion::V4 => nix::libc::AF_INET as u8,
+        IpVersion::V6 => nix::libc::AF_INET6 as u8,
+    }
+}
+
+fn family_version(family: u8) -> Option<IpVersion> {
+    [IpVersion::V4, IpVersion::V6]
+        .into_iter()
+        .find(|version| libc_family(*version) == family)
+}
+
+fn address(value: &[u8]) -> Option<IpAddr> {
+    match value.len() {
+        4 => Some(IpAddr::V4(Ipv4Addr::from(<[u8; 4]>::try_from(value).ok()?))),
+        16 => Some(IpAddr::V6(Ipv6Addr::from(
+            <[u8; 16]>::try_from(value).ok()?,
+        ))),
+        _ => None,
+    }
+}
+
+fn network(value: &[u8], prefix_len: u8) -> Option<IpNetwork> {
+    IpNetwork::new(address(value)?, prefix_len).ok()
+}
+
+fn interface_name(value: &[u8]) -> String {
+    let name = value.split(|b| *b == 0).next().unwrap_or_default();
+
+    String::from_utf8_lossy(name).into_owned()
+}
+
+/// Parses a unicast route, along with the table it is in
+fn parse_route(payload: &[u8], names: &HashMap<u32, String>) -> Option<(u32, Route)> {
+    let version = family_version(*payload.first()?)?;
+    let (dst_len, header_table, kind) = (*payload.get(1)?, *payload.get(4)?, *payload.get(7)?);
+    let flags = netlink::u32_value(payload.get(8..)?)?;
+
+    // Cached routes are not part of any table
+    if kind != RTN_UNICAST || flags & RTM_F_CLONED != 0 {
+        return None;
+    }
+
+    let mut table = u32::from(header_table);
+    let mut destination = None;
+    let mut gateway = None;
+    let mut index = None;
+    let mut metric = 0;
+
+    for (kind, value) in netlink::attributes(payload) {
+        match kind {
+            RTA_DST => destination = address(value),
+            RTA_GATEWAY => gateway = address(value),
+            RTA_OIF => index = netlink::u32_value(value),
+            RTA_PRIORITY => metric = netlink::u32_value(value)?,
+            RTA_TABLE => table = netlink::u32_value(value)?,
+            // Use the first hop of a multipath route, which starts with its length, flags, hops and then index
+            RTA_MULTIPATH if index.is_none() => index = value.get(4..).and_then(netlink::u32_value),
+            _ => {}
+        }
+    }
+
+    let destination = destination.unwrap_or_else(|| version.unspecified());
+
+    Some((
+        table,
+        Route {
+            destination: IpNetwork::new(destination, dst_len).ok()?,
+            gateway: gateway.filter(|gateway| !gateway.is_unspecified()),
+            interface: index
+                .and_then(|index| names.get(&index).cloned())
+                .unwrap_or_default(),
+            metric,
+        },
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn route(destination: &str, interface: &str) -> Route {
+        Route {
+            destination: destination.parse().unwrap(),
+            gateway: None,
+            interface: interface.to_string(),
+            metric: 0,
+        }
+    }
+
+    fn rule(priority: u32, action: RuleAction) -> Rule {
+        Rule {
+            version: IpVersion::V4,
+            priority,
+            source: None,
+            destination: None,
+            fwmark: 0,
+            fwmask: 0,
+            input_interface: None,
+            output_interface: None,
+            invert: false,
+            suppress_prefix_len: None,
+            action,
+        }
+    }
+
+    /// The rules and tables `wg-quick` sets up for a full tunnel
+    fn wg_quick() -> RoutingPolicy {
+        RoutingPolicy {
+            rules: vec![
+                rule(0, RuleAction::Lookup(LOCAL_TABLE)),
+                Rule {
+                    suppress_prefix_len: Some(0),
+                    ..rule(32764, RuleAction::Lookup(MAIN_TABLE))
+                },
+                Rule {
+                    fwmark: 51820,
+                    fwmask: u32::MAX,
+                    invert: true,
+                    ..rule(32765, RuleAction::Lookup(51820))
+                },
+                rule(32766, RuleAction::Lookup(MAIN_TABLE)),
+            ],
+            tables: BTreeMap::from([
+                (
+                    MAIN_TABLE,
+                    vec![route("0.0.0.0/0", "eth0"), route("192.168.1.0/24", "eth0")],
+                ),
+                (51820, vec![route("0.0.0.0/0", "wg0")]),
+            ]),
+        }
+    }
+
+    #[test]
+    fn test_wg_quick() {
+        let policy = wg_quick();
+
+        let internet = policy.route_to("1.1.1.1".parse().unwrap(), 0).unwrap();
+        assert_eq!(internet.interface, "wg0");
+
+        // The tunnel's own packets are marked, so they skip the tunnel table
+        let marked = policy.route_to("1.1.1.1".parse().unwrap(), 51820).unwrap();
+        assert_eq!(marked.interface, "eth0");
+
+        // More specific routes in the main table are not suppressed
+        let lan = policy.route_to("192.168.1.20".parse().unwrap(), 0).unwrap();
+        assert_eq!(lan.interface, "eth0");
+    }
+
+    #[test]
+    fn test_rules() {
+        let mut policy = wg_quick();
+        policy.rules.insert(1, rule(100, RuleAction::Reject));
+        assert_eq!(policy.route_to("1.1.1.1".parse().unwrap(), 0), None);
+
+        // Without any rules, only the main table is used
+        let policy = RoutingPolicy::from(vec![route("0.0.0.0/0", "eth0")]);
+        assert_eq!(
+            policy
+                .route_to("1.1.1.1".parse().unwrap(), 0)
+                .unwrap()
+                .interface,
+            "eth0"
+        );
+
+        let from = Rule {
+            source: Some("10.0.0.0/8".parse().unwrap()),
+            ..rule(1, RuleAction::Other)
+        };
+        assert!(!from.matches("1.1.1.1".parse().unwrap(), 0));
+    }
+
+    #[test]
+    fn test_system_policy() {
+        // Netlink may be unavailable in a sandbox
+        if let Ok(policy) = RoutingPolicy::get() {
+            assert!(policy
+                .rules
+                .iter()
+                .any(|rule| rule.action == RuleAction::Lookup(MAIN_TABLE)));
+        }
+    }
+}
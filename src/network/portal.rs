@@ -0,0 +1,536 @@
+//! Captive portal detection
+//!
+//! Networks behind a captive portal (hotels, airports, etc.) usually report full internet connectivity,
+//! but redirect every request to a login page. This module detects that by requesting a known check URL,
+//! and comparing the response to what is expected.
+//!
+//! Where [`Connectivity`](super::Connectivity) is available, the check is only made when it reports Internet
+//! connectivity, as without it there is nothing for a portal to intercept.
+
+use std::{
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+#[cfg(any(windows, target_os = "linux"))]
+use super::{
+    provider::{ConnectivityProvider, SystemProvider},
+    Connectivity,
+};
+
+/// The default URL used to check for a captive portal
+///
+/// This URL always returns an empty `204 No Content` response when there is no captive portal
+pub const DEFAULT_CHECK_URL: &str = "http://connectivitycheck.gstatic.com/generate_204";
+
+/// The most bytes read from a response, including its headers
+///
+/// Check URLs respond with little or nothing, so this only bounds how much a portal, or anything else intercepting the
+/// request, can make us read.
+pub const MAX_RESPONSE_LEN: u64 = 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when checking for a captive portal
+pub enum Error {
+    #[error("Invalid check URL: {0}")]
+    /// The check URL could not be parsed, or does not use the `http` scheme
+    InvalidUrl(String),
+    #[error("IO error: {0}")]
+    /// The request could not be sent, or the response could not be read
+    Io(#[from] std::io::Error),
+    #[error("Invalid HTTP response")]
+    /// The server responded with something that is not valid HTTP
+    InvalidResponse,
+    #[error("The response is longer than {MAX_RESPONSE_LEN} bytes")]
+    /// The server responded with more than [`MAX_RESPONSE_LEN`] bytes
+    ResponseTooLarge,
+    #[cfg(any(windows, target_os = "linux"))]
+    #[error("No Internet connectivity: {0:?}")]
+    /// There is no Internet connectivity, so there is no portal to detect
+    NoInternet(Connectivity),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The result of a captive portal check
+pub enum PortalStatus {
+    /// The check URL returned the expected response, so traffic is not being intercepted
+    Open,
+    /// The check URL returned an unexpected response, so traffic is being intercepted
+    Portal {
+        /// Where the portal redirected the request to, if it was redirected
+        redirect_url: Option<String>,
+    },
+}
+
+impl PortalStatus {
+    #[must_use]
+    /// Checks if traffic is being intercepted by a captive portal
+    pub fn is_portal(&self) -> bool {
+        matches!(self, PortalStatus::Portal { .. })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[must_use]
+/// A configurable captive portal check
+///
+/// # Examples
+///
+/// ```no_run
+/// # use quork::network::portal::PortalCheck;
+/// let status = PortalCheck::default().check().unwrap();
+///
+/// if status.is_portal() {
+///     println!("Please log in to the network");
+/// }
+/// ```
+pub struct PortalCheck {
+    url: String,
+    expected_status: u16,
+    expected_body: Option<String>,
+    timeout: Duration,
+}
+
+impl Default for PortalCheck {
+    fn default() -> Self {
+        Self::new(DEFAULT_CHECK_URL)
+    }
+}
+
+impl PortalCheck {
+    /// Construct a new [`PortalCheck`] against the provided URL
+    ///
+    /// By default this expects an empty `204 No Content` response
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            expected_status: 204,
+            expected_body: Some(String::new()),
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    /// Set the status code the check URL is expected to return
+    pub fn with_status(self, expected_status: u16) -> Self {
+        Self {
+            expected_status,
+            ..self
+        }
+    }
+
+    /// Set the body the check URL is expected to return
+    ///
+    /// Passing [`None`] will accept any body
+    pub fn with_body(self, expected_body: Option<impl Into<String>>) -> Self {
+        Self {
+            expected_body: expected_body.map(Into::into),
+            ..self
+        }
+    }
+
+    /// Set the timeout for connecting, and for each read and write
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+
+    /// Request the check URL and compare the response with what is expected
+    ///
+    /// # Errors
+    /// - The check URL is invalid
+    /// - The request fails to send, or the response fails to read
+    /// - The response is not valid HTTP, or is longer than [`MAX_RESPONSE_LEN`]
+    pub fn check(&self) -> Result<PortalStatus, Error> {
+        let response = get(&self.url, self.timeout)?;
+
+        let body_matches = self
+            .expected_body
+            .as_ref()
+            .map_or(true, |expected| expected.as_bytes() == response.body);
+
+        if response.status == self.expected_status && body_matches {
+            Ok(PortalStatus::Open)
+        } else {
+            Ok(PortalStatus::Portal {
+                redirect_url: response.location,
+            })
+        }
+    }
+
+    #[cfg(any(windows, target_os = "linux"))]
+    /// Request the check URL, if the provider reports Internet connectivity
+    ///
+    /// If the provider fails to get the connectivity, the check is made anyway.
+    ///
+    /// # Errors
+    /// - The provider reports no Internet connectivity
+    /// - See [`PortalCheck::check`]
+    pub fn check_with(&self, provider: impl ConnectivityProvider) -> Result<PortalStatus, Error> {
+        match provider.connectivity() {
+            Some(connectivity) if !connectivity.is_internet() => {
+                Err(Error::NoInternet(connectivity))
+            }
+            _ => self.check(),
+        }
+    }
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+impl Connectivity {
+    /// Checks for a captive portal using the default check URL, if this is Internet connectivity
+    ///
+    /// # Errors
+    /// - This is not Internet connectivity
+    /// - See [`PortalCheck::check`]
+    pub fn captive_portal(self) -> Result<PortalStatus, Error> {
+        if self.is_internet() {
+            PortalCheck::default().check()
+        } else {
+            Err(Error::NoInternet(self))
+        }
+    }
+}
+
+/// Checks for a captive portal using the default check URL
+///
+/// Where supported, the system connectivity is checked first.
+///
+/// # Errors
+/// - There is no Internet connectivity
+/// - See [`PortalCheck::check`]
+pub fn detect() -> Result<PortalStatus, Error> {
+    #[cfg(any(windows, target_os = "linux"))]
+    return PortalCheck::default().check_with(SystemProvider::new());
+
+    #[cfg(not(any(windows, target_os = "linux")))]
+    PortalCheck::default().check()
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    location: Option<String>,
+    body: Vec<u8>,
+}
+
+/// Splits an `http://` URL into its host, port and request target, which is the path and query
+fn parse_url(url: &str) -> Result<(&str, u16, String), Error> {
+    let invalid = || Error::InvalidUrl(url.to_string());
+
+    let rest = url.strip_prefix("http://").ok_or_else(invalid)?;
+    let (authority, rest) = rest
+        .find(['/', '?', '#'])
+        .map_or((rest, ""), |i| rest.split_at(i));
+
+    // The fragment is never sent to the server
+    let target = rest.split('#').next().unwrap_or_default();
+    let target = if target.starts_with('/') {
+        target.to_string()
+    } else {
+        format!("/{target}")
+    };
+
+    let (host, port) = match authority.rsplit_once(':') {
+        // Ignore colons inside of IPv6 literals
+        Some((host, port)) if !port.contains(']') => (host, port.parse().map_err(|_| invalid())?),
+        _ => (authority, 80),
+    };
+
+    if host.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok((host, port, target))
+}
+
+fn get(url: &str, timeout: Duration) -> Result<Response, Error> {
+    let (host, port, target) = parse_url(url)?;
+
+    let mut stream = connect(host, port, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    // The port must be included in the host header, unless it is the default
+    let host = if port == 80 {
+        host.to_string()
+    } else {
+        format!("{host}:{port}")
+    };
+
+    let request = format!(
+        "GET {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: quork\r\nAccept: */*\r\nConnection: close\r\n\r\n"
+    );
+    stream.write_all(request.as_bytes())?;
+
+    // Read one byte past the limit, to tell a response of exactly the limit from a longer one
+    let mut raw = Vec::new();
+    stream.take(MAX_RESPONSE_LEN + 1).read_to_end(&mut raw)?;
+    if raw.len() as u64 > MAX_RESPONSE_LEN {
+        return Err(Error::ResponseTooLarge);
+    }
+
+    parse_response(&raw)
+}
+
+/// Connects to each resolved address in turn, until one succeeds
+fn connect(host: &str, port: u16, timeout: Duration) -> Result<TcpStream, Error> {
+    let addrs = (host.trim_start_matches('[').trim_end_matches(']'), port).to_socket_addrs()?;
+
+    let mut last_error = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    Err(Error::Io(last_error.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("{host} did not resolve to any addresses"),
+        )
+    })))
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response, Error> {
+    let header_end = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or(Error::InvalidResponse)?;
+
+    let head = std::str::from_utf8(&raw[..header_end]).map_err(|_| Error::InvalidResponse)?;
+    let mut body = &raw[header_end + 4..];
+
+    let mut lines = head.split("\r\n");
+
+    let status = lines
+        .next()
+        .and_then(|line| {
+            let mut parts = line.split_whitespace();
+            parts.next().filter(|v| v.starts_with("HTTP/"))?;
+            parts.next()?.parse().ok()
+        })
+        .ok_or(Error::InvalidResponse)?;
+
+    let mut location = None;
+    let mut chunked = false;
+
+    for line in lines {
+        let (name, value) = line.split_once(':').ok_or(Error::InvalidResponse)?;
+        let value = value.trim();
+
+        if name.eq_ignore_ascii_case("location") {
+            location = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("content-length") {
+            let length: usize = value.parse().map_err(|_| Error::InvalidResponse)?;
+            body = body.get(..length).ok_or(Error::InvalidResponse)?;
+        } else if name.eq_ignore_ascii_case("transfer-encoding") {
+            chunked = value.eq_ignore_ascii_case("chunked");
+        }
+    }
+
+    let body = if chunked {
+        decode_chunked(body)?
+    } else {
+        body.to_vec()
+    };
+
+    Ok(Response {
+        status,
+        location,
+        body,
+    })
+}
+
+fn decode_chunked(mut raw: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut body = Vec::new();
+
+    loop {
+        let line_end = raw
+            .windows(2)
+            .position(|w| w == b"\r\n")
+            .ok_or(Error::InvalidResponse)?;
+
+        let size = std::str::from_utf8(&raw[..line_end])
+            .ok()
+            .and_then(|line| {
+                // Ignore any chunk extensions
+                let size = line.split(';').next()?.trim();
+                usize::from_str_radix(size, 16).ok()
+            })
+            .ok_or(Error::InvalidResponse)?;
+
+        raw = &raw[line_end + 2..];
+
+        if size == 0 {
+            return Ok(body);
+        }
+
+        body.extend_from_slice(raw.get(..size).ok_or(Error::InvalidResponse)?);
+        raw = raw.get(size + 2..).ok_or(Error::InvalidResponse)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, sync::mpsc, thread};
+
+    use super::*;
+
+    /// Serves a single canned response on loopback, and returns the URL to request
+    fn serve(response: &'static str) -> String {
+        serve_with_request(response).0
+    }
+
+    /// Like [`serve`], but also returns the request the server received
+    fn serve_with_request(response: &'static str) -> (String, mpsc::Receiver<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (tx, rx) = mpsc::channel();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            // Read the whole request, so the connection isn't reset when it is closed
+            let mut request = Vec::new();
+            while !request.ends_with(b"\r\n\r\n") {
+                let mut buf = [0; 1024];
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+
+            // The client may stop reading before the end of an oversized response
+            _ = stream.write_all(response.as_bytes());
+            _ = tx.send(String::from_utf8(request).unwrap());
+        });
+
+        (format!("http://{addr}/generate_204"), rx)
+    }
+
+    #[test]
+    fn test_open() {
+        let url = serve("HTTP/1.1 204 No Content\r\nContent-Length: 0\r\n\r\n");
+
+        assert_eq!(PortalCheck::new(url).check().unwrap(), PortalStatus::Open);
+    }
+
+    #[test]
+    fn test_redirect() {
+        let url = serve(
+            "HTTP/1.1 302 Found\r\nLocation: http://portal.example/login\r\nContent-Length: 0\r\n\r\n",
+        );
+
+        assert_eq!(
+            PortalCheck::new(url).check().unwrap(),
+            PortalStatus::Portal {
+                redirect_url: Some("http://portal.example/login".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn test_unexpected_body() {
+        let url = serve("HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nlogin");
+
+        let check = PortalCheck::new(url)
+            .with_status(200)
+            .with_body(Some("success"));
+
+        assert_eq!(
+            check.check().unwrap(),
+            PortalStatus::Portal { redirect_url: None }
+        );
+    }
+
+    #[test]
+    fn test_chunked_body() {
+        let url = serve(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\nsucc\r\n3\r\ness\r\n0\r\n\r\n",
+        );
+
+        let check = PortalCheck::new(url)
+            .with_status(200)
+            .with_body(Some("success"));
+
+        assert_eq!(check.check().unwrap(), PortalStatus::Open);
+    }
+
+    #[test]
+    fn test_response_too_large() {
+        let response = format!(
+            "HTTP/1.1 200 OK\r\n\r\n{}",
+            "a".repeat(usize::try_from(MAX_RESPONSE_LEN).unwrap())
+        );
+        let url = serve(Box::leak(response.into_boxed_str()));
+
+        assert!(matches!(
+            PortalCheck::new(url).check(),
+            Err(Error::ResponseTooLarge)
+        ));
+    }
+
+    #[test]
+    fn test_host_header() {
+        let (url, request) = serve_with_request("HTTP/1.1 204 No Content\r\n\r\n");
+        let port = url
+            .split(':')
+            .nth(2)
+            .unwrap()
+            .trim_end_matches("/generate_204");
+
+        // `localhost` may resolve to `::1` first, which nothing is listening on
+        let url = format!("http://localhost:{port}/generate_204");
+        assert_eq!(PortalCheck::new(url).check().unwrap(), PortalStatus::Open);
+
+        let request = request.recv().unwrap();
+        assert!(request.contains(&format!("\r\nHost: localhost:{port}\r\n")));
+    }
+
+    #[cfg(any(windows, target_os = "linux"))]
+    #[test]
+    fn test_check_with() {
+        use crate::network::FakeProvider;
+
+        let check = PortalCheck::new(serve("HTTP/1.1 204 No Content\r\n\r\n"));
+
+        assert!(matches!(
+            check.check_with(FakeProvider::offline()),
+            Err(Error::NoInternet(Connectivity::Disconnected))
+        ));
+        assert_eq!(
+            check
+                .check_with(FakeProvider::new(Connectivity::Ipv4Internet))
+                .unwrap(),
+            PortalStatus::Open
+        );
+        assert!(matches!(
+            Connectivity::Ipv4Subnet.captive_portal(),
+            Err(Error::NoInternet(Connectivity::Ipv4Subnet))
+        ));
+    }
+
+    #[test]
+    fn test_parse_url() {
+        assert_eq!(
+            parse_url("http://example.com").unwrap(),
+            ("example.com", 80, "/".to_string())
+        );
+        assert_eq!(
+            parse_url("http://[::1]:8080/check").unwrap(),
+            ("[::1]", 8080, "/check".to_string())
+        );
+        assert_eq!(
+            parse_url("http://example.com?probe=1").unwrap(),
+            ("example.com", 80, "/?probe=1".to_string())
+        );
+        assert_eq!(
+            parse_url("http://example.com:8080#top").unwrap(),
+            ("example.com", 8080, "/".to_string())
+        );
+        assert_eq!(
+            parse_url("http://example.com/check?probe=1#top").unwrap(),
+            ("example.com", 80, "/check?probe=1".to_string())
+        );
+        assert!(parse_url("https://example.com").is_err());
+    }
+}
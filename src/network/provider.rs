@@ -0,0 +1,317 @@
+//! Swappable sources of [`Connectivity`]
+//!
+//! Code that takes a [`ConnectivityProvider`] rather than calling [`Connectivity::get`] directly can be tested against
+//! a [`FakeProvider`], or forced offline with the [`FORCE_OFFLINE_VAR`] environment variable.
+
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Condvar, Mutex, PoisonError},
+    time::Duration,
+};
+
+use super::Connectivity;
+
+/// Setting this environment variable to `1`, `true`, `yes` or `on` makes [`Connectivity::try_get`],
+/// [`Connectivity::get`] and [`SystemProvider`] report [`Connectivity::Disconnected`]
+pub const FORCE_OFFLINE_VAR: &str = "QUORK_FORCE_OFFLINE";
+
+/// A source of the current [`Connectivity`]
+pub trait ConnectivityProvider {
+    /// Gets the current connectivity
+    ///
+    /// Returns `None` if the connectivity could not be determined.
+    fn connectivity(&self) -> Option<Connectivity>;
+
+    /// Blocks until the connectivity may have changed, or the timeout passes
+    ///
+    /// By default this just sleeps for the timeout.
+    fn wait_for_change(&self, timeout: Duration) {
+        std::thread::sleep(timeout);
+    }
+}
+
+impl<P: ConnectivityProvider + ?Sized> ConnectivityProvider for &P {
+    fn connectivity(&self) -> Option<Connectivity> {
+        (**self).connectivity()
+    }
+
+    fn wait_for_change(&self, timeout: Duration) {
+        (**self).wait_for_change(timeout);
+    }
+}
+
+impl<P: ConnectivityProvider + ?Sized> ConnectivityProvider for Arc<P> {
+    fn connectivity(&self) -> Option<Connectivity> {
+        (**self).connectivity()
+    }
+
+    fn wait_for_change(&self, timeout: Duration) {
+        (**self).wait_for_change(timeout);
+    }
+}
+
+#[derive(Debug)]
+#[must_use]
+/// Reports the host's real connectivity
+///
+/// The connectivity can be forced offline with [`SystemProvider::with_force_offline`], or otherwise with the
+/// [`FORCE_OFFLINE_VAR`] environment variable.
+pub struct SystemProvider {
+    force_offline: Option<bool>,
+    #[cfg(target_os = "linux")]
+    watcher: Option<super::watch::Watcher>,
+}
+
+impl Default for SystemProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemProvider {
+    /// Construct a new [`SystemProvider`]
+    pub fn new() -> Self {
+        Self {
+            force_offline: None,
+            #[cfg(target_os = "linux")]
+            watcher: super::watch::Watcher::new(),
+        }
+    }
+
+    /// Set whether the connectivity is forced offline, overriding the [`FORCE_OFFLINE_VAR`] environment variable
+    pub fn with_force_offline(self, force_offline: bool) -> Self {
+        Self {
+            force_offline: Some(force_offline),
+            ..self
+        }
+    }
+
+    #[must_use]
+    /// Checks if the connectivity is forced offline
+    pub fn is_forced_offline(&self) -> bool {
+        self.force_offline.unwrap_or_else(is_forced_offline_by_env)
+    }
+}
+
+impl ConnectivityProvider for SystemProvider {
+    fn connectivity(&self) -> Option<Connectivity> {
+        if self.is_forced_offline() {
+            return Some(Connectivity::Disconnected);
+        }
+
+        // The environment variable has already been checked, and may have been overridden
+        Connectivity::detect().ok()
+    }
+
+    fn wait_for_change(&self, timeout: Duration) {
+        #[cfg(target_os = "linux")]
+        if let Some(watcher) = &self.watcher {
+            watcher.wait(timeout);
+            return;
+        }
+
+        std::thread::sleep(timeout);
+    }
+}
+
+/// Checks if the connectivity is forced offline by [`FORCE_OFFLINE_VAR`]
+pub(crate) fn is_forced_offline_by_env() -> bool {
+    std::env::var(FORCE_OFFLINE_VAR).is_ok_and(|value| is_truthy(&value))
+}
+
+fn is_truthy(value: &str) -> bool {
+    ["1", "true", "yes", "on"]
+        .iter()
+        .any(|truthy| value.trim().eq_ignore_ascii_case(truthy))
+}
+
+#[derive(Debug)]
+struct FakeState {
+    current: Connectivity,
+    script: VecDeque<Connectivity>,
+    /// Incremented by every call to [`FakeProvider::set`]
+    generation: u64,
+    /// The generation when the connectivity was last reported
+    seen: u64,
+}
+
+#[derive(Debug)]
+/// A scriptable provider for tests
+///
+/// # Examples
+///
+/// ```
+/// # use quork::network::{provider::{ConnectivityProvider, FakeProvider}, Connectivity};
+/// let provider = FakeProvider::offline().with_script([Connectivity::Ipv4Subnet, Connectivity::Ipv4Internet]);
+///
+/// assert_eq!(provider.connectivity(), Some(Connectivity::Ipv4Subnet));
+/// assert_eq!(provider.connectivity(), Some(Connectivity::Ipv4Internet));
+/// // Once the script runs out, the last value is repeated
+/// assert_eq!(provider.connectivity(), Some(Connectivity::Ipv4Internet));
+/// ```
+pub struct FakeProvider {
+    state: Mutex<FakeState>,
+    changed: Condvar,
+}
+
+impl FakeProvider {
+    #[must_use]
+    /// Construct a new [`FakeProvider`] that reports the provided connectivity
+    pub fn new(current: Connectivity) -> Self {
+        Self {
+            state: Mutex::new(FakeState {
+                current,
+                script: VecDeque::new(),
+                generation: 0,
+                seen: 0,
+            }),
+            changed: Condvar::new(),
+        }
+    }
+
+    #[must_use]
+    /// Construct a new [`FakeProvider`] that reports [`Connectivity::Disconnected`]
+    pub fn offline() -> Self {
+        Self::new(Connectivity::Disconnected)
+    }
+
+    #[must_use]
+    /// Set the values reported by successive calls, after which the last value is repeated
+    pub fn with_script(self, script: impl IntoIterator<Item = Connectivity>) -> Self {
+        self.lock().script.extend(script);
+        self
+    }
+
+    /// Replaces the reported connectivity, discarding any remaining script, and wakes anything waiting for a change
+    pub fn set(&self, current: Connectivity) {
+        let mut state = self.lock();
+        state.current = current;
+        state.script.clear();
+        state.generation += 1;
+
+        self.changed.notify_all();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FakeState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ConnectivityProvider for FakeProvider {
+    fn connectivity(&self) -> Option<Connectivity> {
+        let mut state = self.lock();
+
+        if let Some(next) = state.script.pop_front() {
+            state.current = next;
+        }
+        state.seen = state.generation;
+
+        Some(state.current)
+    }
+
+    /// Returns as soon as the connectivity is set, including if it was set since it was last reported
+    fn wait_for_change(&self, timeout: Duration) {
+        let state = self.lock();
+
+        // The next scripted value is a change, so there is no need to wait for it
+        if state.script.is_empty() {
+            drop(
+                self.changed
+                    .wait_timeout_while(state, timeout, |state| state.generation == state.seen)
+                    .unwrap_or_else(PoisonError::into_inner),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    #[test]
+    fn test_force_offline() {
+        assert!(is_truthy("1"));
+        assert!(is_truthy(" TRUE "));
+        assert!(!is_truthy("0"));
+        assert!(!is_truthy(""));
+
+        let provider = SystemProvider::new().with_force_offline(true);
+        assert!(provider.is_forced_offline());
+        assert_eq!(provider.connectivity(), Some(Connectivity::Disconnected));
+        assert_eq!(
+            Connectivity::wait_until_with(&provider, |conn| conn.is_connected(), Duration::ZERO),
+            None
+        );
+
+        let provider = SystemProvider::new().with_force_offline(false);
+        assert!(!provider.is_forced_offline());
+        assert_eq!(provider.connectivity(), Connectivity::detect().ok());
+    }
+
+    #[test]
+    fn test_fake_script() {
+        let provider = FakeProvider::offline().with_script([
+            Connectivity::Ipv4Subnet,
+            Connectivity::Ipv4Localnetwork,
+            Connectivity::Ipv4Internet,
+        ]);
+
+        let start = Instant::now();
+        let result = Connectivity::wait_until_with(
+            &provider,
+            |conn| conn.is_internet(),
+            Duration::from_secs(10),
+        );
+
+        assert_eq!(result, Some(Connectivity::Ipv4Internet));
+        // Scripted changes are reported without waiting out the poll interval
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_fake_set_before_wait() {
+        let provider = FakeProvider::offline();
+        assert_eq!(provider.connectivity(), Some(Connectivity::Disconnected));
+
+        // A change between checking and waiting is not lost
+        provider.set(Connectivity::Ipv4Internet);
+
+        let start = Instant::now();
+        provider.wait_for_change(Duration::from_secs(10));
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_fake_set() {
+        let provider = Arc::new(FakeProvider::offline());
+
+        let waiting = {
+            let provider = provider.clone();
+            std::thread::spawn(move || {
+                Connectivity::wait_until_with(
+                    &provider,
+                    |conn| conn.is_routed(),
+                    Duration::from_secs(10),
+                )
+            })
+        };
+
+        std::thread::sleep(Duration::from_millis(50));
+        provider.set(Connectivity::Ipv6Localnetwork);
+
+        assert_eq!(
+            waiting.join().unwrap(),
+            Some(Connectivity::Ipv6Localnetwork)
+        );
+        assert_eq!(
+            Connectivity::wait_until_with(
+                &provider,
+                |conn| conn.is_internet(),
+                Duration::from_millis(50)
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Route table lookup
+//!
+//! Reads the kernel routing table from `/proc/net/route` and `/proc/net/ipv6_route`.
+//!
+//! These only list the main routing table. Routes in other tables, which are selected by policy routing rules (such
+//! as those added by `wg-quick`), are not visible here.
+
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::{IpNetwork, IpVersion};
+
+const IPV4_ROUTE_PATH: &str = "/proc/net/route";
+const IPV6_ROUTE_PATH: &str = "/proc/net/ipv6_route";
+
+const RTF_UP: u32 = 0x0001;
+const RTF_GATEWAY: u32 = 0x0002;
+const RTF_REJECT: u32 = 0x0200;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when reading the route table
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The route table could not be read
+    Io(#[from] std::io::Error),
+    #[error("Invalid route table entry: {0:?}")]
+    /// A line of the route table could not be parsed
+    InvalidEntry(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// A single entry in the kernel routing table
+pub struct Route {
+    /// The destination network
+    pub destination: IpNetwork,
+    /// The next hop, if traffic is routed through a gateway
+    pub gateway: Option<IpAddr>,
+    /// The name of the egress interface
+    pub interface: String,
+    /// The route metric. Lower metrics are preferred
+    pub metric: u32,
+}
+
+impl Route {
+    #[must_use]
+    /// Gets the [`IpVersion`] of the route
+    pub fn ip_version(&self) -> IpVersion {
+        self.destination.ip_version()
+    }
+
+    #[must_use]
+    /// Checks if this is a default route (i.e `0.0.0.0/0` or `::/0`)
+    pub fn is_default(&self) -> bool {
+        self.destination.prefix_len() == 0
+    }
+
+    #[must_use]
+    /// Checks if the provided address falls within the destination of this route
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        self.destination.contains(addr)
+    }
+}
+
+/// Lists all active routes in the main routing table, for both IPv4 and IPv6
+///
+/// Unreachable and prohibit routes are skipped, as traffic cannot be sent over them.
+///
+/// # Errors
+/// - The route tables could not be read
+/// - The route tables contain an invalid entry
+pub fn routes() -> Result<Vec<Route>, Error> {
+    let mut routes = parse_ipv4(&std::fs::read_to_string(IPV4_ROUTE_PATH)?)?;
+
+    // The IPv6 route table does not exist if IPv6 is disabled
+    match std::fs::read_to_string(IPV6_ROUTE_PATH) {
+        Ok(table) => routes.extend(parse_ipv6(&table)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    Ok(routes)
+}
+
+/// Finds the route the kernel would use to reach the provided address
+///
+/// The most specific route is chosen, with ties broken by the lowest metric.
+///
+/// # Errors
+/// - See [`routes`]
+pub fn route_to(addr: IpAddr) -> Result<Option<Route>, Error> {
+    Ok(best_route(routes()?, addr))
+}
+
+/// Finds the default route for the provided [`IpVersion`]
+///
+/// # Errors
+/// - See [`routes`]
+pub fn default_route(version: IpVersion) -> Result<Option<Route>, Error> {
+    Ok(routes()?
+        .into_iter()
+        .filter(|route| route.is_default() && route.ip_version() == version)
+        .min_by_key(|route| route.metric))
+}
+
+pub(crate) fn best_route(routes: Vec<Route>, addr: IpAddr) -> Option<Route> {
+    routes
+        .into_iter()
+        .filter(|route| route.contains(addr))
+        .max_by(|a, b| {
+            a.destination
+                .prefix_len()
+                .cmp(&b.destination.prefix_len())
+                .then(b.metric.cmp(&a.metric))
+        })
+}
+
+fn parse_ipv4(table: &str) -> Result<Vec<Route>, Error> {
+    let mut routes = Vec::new();
+
+    // Skip the header line
+    for line in table.lines().skip(1).filter(|line| !line.trim().is_empty()) {
+        let invalid = || Error::InvalidEntry(line.to_string());
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [interface, destination, gateway, flags, _refcnt, _use, metric, mask, ..] = fields[..]
+        else {
+            return Err(invalid());
+        };
+
+        let hex = |v: &str| u32::from_str_radix(v, 16).map_err(|_| invalid());
+        // The kernel prints the addresses as native-endian integers of network-order bytes
+        let addr = |v: &str| hex(v).map(|v| Ipv4Addr::from(v.to_ne_bytes()));
+
+        let flags = hex(flags)?;
+        if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 {
+            continue;
+        }
+
+        let gateway = addr(gateway)?;
+
+        routes.push(Route {
+            #[allow(clippy::cast_possible_truncation)]
+            destination: IpNetwork::new(
+                IpAddr::V4(addr(destination)?),
+                u32::from(addr(mask)?).count_ones() as u8,
+            )
+            .map_err(|_| invalid())?,
+            gateway: (flags & RTF_GATEWAY != 0 && !gateway.is_unspecified())
+                .then_some(IpAddr::V4(gateway)),
+            interface: interface.to_string(),
+            metric: metric.parse().map_err(|_| invalid())?,
+        });
+    }
+
+    Ok(routes)
+}
+
+fn parse_ipv6(table: &str) -> Result<Vec<Route>, Error> {
+    let mut routes = Vec::new();
+
+    for line in table.lines().filter(|line| !line.trim().is_empty()) {
+        let invalid = || Error::InvalidEntry(line.to_string());
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [destination, prefix_len, _source, _source_prefix_len, gateway, metric, _refcnt, _use, flags, interface] =
+            fields[..]
+        else {
+            return Err(invalid());
+        };
+
+        let hex = |v: &str| u32::from_str_radix(v, 16).map_err(|_| invalid());
+        let addr = |v: &str| {
+            u128::from_str_radix(v, 16)
+                .map(Ipv6Addr::from)
+                .map_err(|_| invalid())
+        };
+
+        let flags = hex(flags)?;
+        if flags & RTF_UP == 0 || flags & RTF_REJECT != 0 {
+            continue;
+        }
+
+        let gateway = addr(gateway)?;
+
+        routes.push(Route {
+            destination: IpNetwork::new(
+                IpAddr::V6(addr(destination)?),
+                u8::from_str_radix(prefix_len, 16).map_err(|_| invalid())?,
+            )
+            .map_err(|_| invalid())?,
+            gateway: (flags & RTF_GATEWAY != 0 && !gateway.is_unspecified())
+                .then_some(IpAddr::V6(gateway)),
+            interface: interface.to_string(),
+            metric: hex(metric)?,
+        });
+    }
+
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IPV4_TABLE: &str =
+        "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\t\tMTU\tWindow\tIRTT
+eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\t0\t0\t0
+eth0\t0001A8C0\t00000000\t0001\t0\t0\t100\t00FFFFFF\t0\t0\t0
+wg0\t0000000A\t00000000\t0001\t0\t0\t50\t000000FF\t0\t0\t0
+*\t0000A8C0\t00000000\t0201\t0\t0\t0\t0000FFFF\t0\t0\t0
+";
+
+    const IPV6_TABLE: &str = "\
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 fe800000000000000000000000000001 00000400 00000001 00000000 00000003 eth0
+20010db8000000000000000000000000 40 00000000000000000000000000000000 00 00000000000000000000000000000000 00000100 00000001 00000000 00000001 eth0
+00000000000000000000000000000000 00 00000000000000000000000000000000 00 00000000000000000000000000000000 ffffffff 00000001 00000000 00200200 lo
+";
+
+    #[test]
+    fn test_parse_ipv4() {
+        let routes = parse_ipv4(IPV4_TABLE).unwrap();
+
+        // The unreachable route is skipped
+        assert_eq!(routes.len(), 3);
+        assert!(routes[0].is_default());
+        assert_eq!(
+            routes[0].gateway,
+            Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 1)))
+        );
+        assert_eq!(routes[1].destination, "192.168.1.0/24".parse().unwrap());
+        assert_eq!(routes[1].gateway, None);
+    }
+
+    #[test]
+    fn test_parse_ipv6() {
+        let routes = parse_ipv6(IPV6_TABLE).unwrap();
+
+        // The reject route on `lo` is skipped
+        assert_eq!(routes.len(), 2);
+        assert!(routes[0].is_default());
+        assert_eq!(routes[0].gateway, Some("fe80::1".parse().unwrap()));
+        assert_eq!(routes[1].destination.prefix_len(), 64);
+        assert_eq!(routes[1].ip_version(), IpVersion::V6);
+    }
+
+    #[test]
+    fn test_best_route() {
+        let routes = parse_ipv4(IPV4_TABLE).unwrap();
+
+        let lan = best_route(routes.clone(), "192.168.1.20".parse().unwrap()).unwrap();
+        assert_eq!(lan.destination.prefix_len(), 24);
+
+        let vpn = best_route(routes.clone(), "10.1.2.3".parse().unwrap()).unwrap();
+        assert_eq!(vpn.interface, "wg0");
+
+        let internet = best_route(routes, "1.1.1.1".parse().unwrap()).unwrap();
+        assert!(internet.is_default());
+    }
+
+    #[test]
+    fn test_invalid_entry() {
+        assert!(parse_ipv4("header\neth0 nothex").is_err());
+    }
+
+    #[test]
+    fn test_system_routes() {
+        assert!(routes().is_ok());
+    }
+}
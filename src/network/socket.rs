@@ -0,0 +1,416 @@
+//! Local socket and listening port inventory
+//!
+//! Reads the kernel socket tables from `/proc/net/{tcp,tcp6,udp,udp6}`.
+
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpListener, UdpSocket},
+    ops::RangeInclusive,
+};
+
+use super::IpVersion;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when reading the socket tables
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// A socket table could not be read
+    Io(#[from] std::io::Error),
+    #[error("Invalid socket table entry: {0:?}")]
+    /// A line of a socket table could not be parsed
+    InvalidEntry(String),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The transport protocol of a socket
+pub enum Protocol {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+/// The state of a socket, as reported by the kernel
+///
+/// UDP sockets use the same states, where [`SocketState::Established`] means the socket is connected,
+/// and [`SocketState::Close`] means it is only bound.
+pub enum SocketState {
+    /// The connection is established
+    Established,
+    /// A connection request has been sent
+    SynSent,
+    /// A connection request has been received
+    SynRecv,
+    /// The socket is closed, and the connection is shutting down
+    FinWait1,
+    /// The connection is closed, and the socket is waiting for a shutdown from the remote
+    FinWait2,
+    /// The socket is waiting after close to handle packets still in the network
+    TimeWait,
+    /// The socket is not being used
+    Close,
+    /// The remote has shut down, and is waiting for the socket to close
+    CloseWait,
+    /// The remote has shut down and the socket is closed, waiting for acknowledgement
+    LastAck,
+    /// The socket is listening for incoming connections
+    Listen,
+    /// Both sockets are shut down, but not all data has been sent
+    Closing,
+    /// A state not known to this crate
+    Unknown(u8),
+}
+
+impl From<u8> for SocketState {
+    fn from(state: u8) -> Self {
+        // Values from `include/net/tcp_states.h`
+        match state {
+            0x01 => SocketState::Established,
+            0x02 => SocketState::SynSent,
+            0x03 => SocketState::SynRecv,
+            0x04 => SocketState::FinWait1,
+            0x05 => SocketState::FinWait2,
+            0x06 => SocketState::TimeWait,
+            0x07 => SocketState::Close,
+            0x08 => SocketState::CloseWait,
+            0x09 => SocketState::LastAck,
+            0x0A => SocketState::Listen,
+            0x0B => SocketState::Closing,
+            other => SocketState::Unknown(other),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// A socket on the host
+pub struct SocketInfo {
+    /// The transport protocol of the socket
+    pub protocol: Protocol,
+    /// The local address of the socket
+    pub local: SocketAddr,
+    /// The remote address of the socket. This is unspecified if the socket is not connected
+    pub remote: SocketAddr,
+    /// The state of the socket
+    pub state: SocketState,
+    /// The user id of the socket's owner
+    pub uid: u32,
+    /// The inode of the socket
+    pub inode: u64,
+    /// The id of the process that owns the socket, if it could be found
+    pub pid: Option<u32>,
+}
+
+impl SocketInfo {
+    #[must_use]
+    /// Gets the [`IpVersion`] of the socket
+    pub fn ip_version(&self) -> IpVersion {
+        self.local.into()
+    }
+
+    #[must_use]
+    /// Checks if the socket is accepting traffic from any remote
+    ///
+    /// This is a TCP socket in the [`SocketState::Listen`] state, or an unconnected UDP socket
+    pub fn is_listening(&self) -> bool {
+        match self.protocol {
+            Protocol::Tcp => self.state == SocketState::Listen,
+            Protocol::Udp => self.state == SocketState::Close,
+        }
+    }
+}
+
+const TABLES: [(Protocol, IpVersion, &str); 4] = [
+    (Protocol::Tcp, IpVersion::V4, "/proc/net/tcp"),
+    (Protocol::Tcp, IpVersion::V6, "/proc/net/tcp6"),
+    (Protocol::Udp, IpVersion::V4, "/proc/net/udp"),
+    (Protocol::Udp, IpVersion::V6, "/proc/net/udp6"),
+];
+
+/// Lists the TCP and UDP sockets on the host, for both IPv4 and IPv6
+///
+/// The owning process of each socket is found by searching `/proc/*/fd`.
+/// This is only possible for other users' processes when running as root (see `root::is_root`),
+/// or with the `CAP_SYS_PTRACE` capability. Otherwise only sockets owned by the current user will have a pid.
+///
+/// # Errors
+/// - A socket table could not be read
+/// - A socket table contains an invalid entry
+pub fn sockets() -> Result<Vec<SocketInfo>, Error> {
+    let mut sockets = read_tables()?;
+    let owners = socket_owners();
+
+    for socket in &mut sockets {
+        socket.pid = owners.get(&socket.inode).copied();
+    }
+
+    Ok(sockets)
+}
+
+/// Lists the listening sockets on the host
+///
+/// See [`SocketInfo::is_listening`] for what is considered listening.
+///
+/// # Errors
+/// - See [`sockets`]
+pub fn listening() -> Result<Vec<SocketInfo>, Error> {
+    Ok(sockets()?
+        .into_iter()
+        .filter(SocketInfo::is_listening)
+        .collect())
+}
+
+/// Checks if any TCP or UDP socket is bound to the provided local port
+///
+/// IPv6 sockets bound to `::` are considered to be using the port for IPv4 as well,
+/// as they accept IPv4 traffic unless `IPV6_V6ONLY` is set.
+///
+/// # Errors
+/// - See [`sockets`]
+pub fn is_port_in_use(port: u16, version: IpVersion) -> Result<bool, Error> {
+    Ok(port_in_use(&read_tables()?, port, version))
+}
+
+fn port_in_use(sockets: &[SocketInfo], port: u16, version: IpVersion) -> bool {
+    sockets.iter().any(|socket| {
+        let version_matches = socket.ip_version() == version
+            || (version == IpVersion::V4 && socket.local.ip() == Ipv6Addr::UNSPECIFIED);
+
+        socket.local.port() == port && version_matches && socket.state != SocketState::TimeWait
+    })
+}
+
+/// Finds a free port, by letting the OS assign one
+///
+/// The port is free for both TCP and UDP at the time of the call, but may be taken by the time it is used.
+///
+/// # Errors
+/// - No ports could be bound
+pub fn free_port(version: IpVersion) -> std::io::Result<u16> {
+    let unspecified = version.unspecified();
+
+    // The OS only guarantees the port is free for the protocol it was bound with, so retry until both are free
+    let mut attempts = 0;
+
+    loop {
+        let port = TcpListener::bind((unspecified, 0))?.local_addr()?.port();
+
+        match UdpSocket::bind((unspecified, port)) {
+            Ok(_) => return Ok(port),
+            Err(e) if attempts >= 16 => return Err(e),
+            Err(_) => attempts += 1,
+        }
+    }
+}
+
+/// Finds the first free port in the provided range
+///
+/// # Errors
+/// - See [`sockets`]
+pub fn free_port_in(range: RangeInclusive<u16>, version: IpVersion) -> Result<Option<u16>, Error> {
+    let sockets = read_tables()?;
+    let unspecified = version.unspecified();
+
+    Ok(range.into_iter().find(|&port| {
+        !port_in_use(&sockets, port, version)
+            && TcpListener::bind((unspecified, port)).is_ok()
+            && UdpSocket::bind((unspecified, port)).is_ok()
+    }))
+}
+
+fn read_tables() -> Result<Vec<SocketInfo>, Error> {
+    let mut sockets = Vec::new();
+
+    for (protocol, version, path) in TABLES {
+        match std::fs::read_to_string(path) {
+            Ok(table) => sockets.extend(parse_table(&table, protocol, version)?),
+            // The IPv6 tables do not exist if IPv6 is disabled
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound && version == IpVersion::V6 => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+
+    Ok(sockets)
+}
+
+/// Maps socket inodes to the process that owns them
+fn socket_owners() -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+
+    let Ok(processes) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+
+    for process in processes.flatten() {
+        let Some(pid) = process.file_name().to_str().and_then(|v| v.parse().ok()) else {
+            continue;
+        };
+
+        // Processes owned by other users cannot be read without privileges
+        let Ok(fds) = std::fs::read_dir(process.path().join("fd")) else {
+            continue;
+        };
+
+        for fd in fds.flatten() {
+            let inode = std::fs::read_link(fd.path()).ok().and_then(|target| {
+                target
+                    .to_str()?
+                    .strip_prefix("socket:[")?
+                    .strip_suffix(']')?
+                    .parse()
+                    .ok()
+            });
+
+            if let Some(inode) = inode {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+
+    owners
+}
+
+fn parse_table(
+    table: &str,
+    protocol: Protocol,
+    version: IpVersion,
+) -> Result<Vec<SocketInfo>, Error> {
+    let mut sockets = Vec::new();
+
+    // Skip the header line
+    for line in table.lines().skip(1).filter(|line| !line.trim().is_empty()) {
+        let invalid = || Error::InvalidEntry(line.to_string());
+        let fields: Vec<&str> = line.split_whitespace().collect();
+
+        let [_sl, local, remote, state, _queue, _timer, _retransmits, uid, _timeout, inode, ..] =
+            fields[..]
+        else {
+            return Err(invalid());
+        };
+
+        sockets.push(SocketInfo {
+            protocol,
+            local: parse_addr(local, version).ok_or_else(invalid)?,
+            remote: parse_addr(remote, version).ok_or_else(invalid)?,
+            state: u8::from_str_radix(state, 16).map_err(|_| invalid())?.into(),
+            uid: uid.parse().map_err(|_| invalid())?,
+            inode: inode.parse().map_err(|_| invalid())?,
+            pid: None,
+        });
+    }
+
+    Ok(sockets)
+}
+
+/// Parses an address in the form `0100007F:0050`
+///
+/// The kernel prints the address as native-endian 32 bit words of network-order bytes, and the port in host order
+fn parse_addr(value: &str, version: IpVersion) -> Option<SocketAddr> {
+    let (addr, port) = value.split_once(':')?;
+    let port = u16::from_str_radix(port, 16).ok()?;
+
+    let mut octets = [0u8; 16];
+    let len = match version {
+        IpVersion::V4 => 4,
+        IpVersion::V6 => 16,
+    };
+
+    if addr.len() != len * 2 {
+        return None;
+    }
+
+    for (i, word) in octets[..len].chunks_mut(4).enumerate() {
+        let hex = addr.get(i * 8..i * 8 + 8)?;
+        word.copy_from_slice(&u32::from_str_radix(hex, 16).ok()?.to_ne_bytes());
+    }
+
+    let ip = match version {
+        IpVersion::V4 => IpAddr::V4(Ipv4Addr::new(octets[0], octets[1], octets[2], octets[3])),
+        IpVersion::V6 => IpAddr::V6(Ipv6Addr::from(octets)),
+    };
+
+    Some(SocketAddr::new(ip, port))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Generated on a little-endian machine
+    #[cfg(target_endian = "little")]
+    const TCP_TABLE: &str = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 0100007F:0277 00000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 21436 1 0000000000000000 100 0 0 10 0
+   1: 0F02000A:0016 0202000A:C350 01 00000000:00000000 02:00059FB1 00000000     0        0 48212 4 0000000000000000 20 4 31 10 -1
+";
+
+    #[cfg(target_endian = "little")]
+    const TCP6_TABLE: &str = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000000000000:0050 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 31337 1 0000000000000000 100 0 0 10 0
+   1: 0000000000000000FFFF00000100007F:1F90 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 31338 1 0000000000000000 100 0 0 10 0
+";
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_parse_table() {
+        let sockets = parse_table(TCP_TABLE, Protocol::Tcp, IpVersion::V4).unwrap();
+
+        assert_eq!(sockets.len(), 2);
+        assert_eq!(sockets[0].local, "127.0.0.1:631".parse().unwrap());
+        assert_eq!(sockets[0].state, SocketState::Listen);
+        assert!(sockets[0].is_listening());
+        assert_eq!(sockets[1].remote, "10.0.2.2:50000".parse().unwrap());
+        assert_eq!(sockets[1].state, SocketState::Established);
+        assert_eq!(sockets[1].inode, 48212);
+
+        let sockets = parse_table(TCP6_TABLE, Protocol::Tcp, IpVersion::V6).unwrap();
+
+        assert_eq!(sockets[0].local, "[::]:80".parse().unwrap());
+        assert_eq!(sockets[0].uid, 1000);
+        assert_eq!(sockets[1].local, "[::ffff:127.0.0.1]:8080".parse().unwrap());
+    }
+
+    #[cfg(target_endian = "little")]
+    #[test]
+    fn test_port_in_use() {
+        let mut sockets = parse_table(TCP_TABLE, Protocol::Tcp, IpVersion::V4).unwrap();
+        sockets.extend(parse_table(TCP6_TABLE, Protocol::Tcp, IpVersion::V6).unwrap());
+
+        assert!(port_in_use(&sockets, 631, IpVersion::V4));
+        assert!(!port_in_use(&sockets, 631, IpVersion::V6));
+        // Bound to `::`, so also uses the IPv4 port
+        assert!(port_in_use(&sockets, 80, IpVersion::V4));
+        assert!(!port_in_use(&sockets, 443, IpVersion::V4));
+    }
+
+    #[test]
+    fn test_invalid_entry() {
+        assert!(parse_table("header\n0: nothing", Protocol::Udp, IpVersion::V4).is_err());
+    }
+
+    #[test]
+    fn test_system_sockets() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let socket = sockets()
+            .unwrap()
+            .into_iter()
+            .find(|socket| socket.protocol == Protocol::Tcp && socket.local.port() == port)
+            .unwrap();
+
+        assert!(socket.is_listening());
+        assert_eq!(socket.pid, Some(std::process::id()));
+        assert!(is_port_in_use(port, IpVersion::V4).unwrap());
+    }
+
+    #[test]
+    fn test_free_port() {
+        let port = free_port(IpVersion::V4).unwrap();
+
+        // Other tests may take the port once it is free, so check it can be used and then keep hold of it
+        let listener = TcpListener::bind(("0.0.0.0", port)).unwrap();
+        assert!(is_port_in_use(port, IpVersion::V4).unwrap());
+
+        drop(listener);
+    }
+}
@@ -0,0 +1,98 @@
+//! Host IP stack support detection
+
+use std::net::{Ipv4Addr, Ipv6Addr, TcpListener, TcpStream, UdpSocket};
+
+use super::IpVersion;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+/// Which IP versions the host's network stack supports
+pub struct StackSupport {
+    /// Whether IPv4 is enabled
+    pub ipv4: bool,
+    /// Whether IPv6 is enabled
+    pub ipv6: bool,
+    /// Whether sockets bound to `::` also accept IPv4 traffic
+    pub dual_stack: bool,
+}
+
+impl StackSupport {
+    #[must_use]
+    /// Detects which IP versions the host supports
+    ///
+    /// On Linux, IPv6 is considered disabled if `/proc/net/if_inet6` is missing, or the `disable_ipv6` sysctl is set.
+    /// Otherwise, each version is enabled if a socket can be bound to its loopback address.
+    ///
+    /// Dual-stack support is checked by binding a listener to `::`, and connecting to it over IPv4.
+    pub fn detect() -> Self {
+        let ipv4 = ipv4_enabled();
+        let ipv6 = ipv6_enabled();
+
+        Self {
+            ipv4,
+            ipv6,
+            dual_stack: ipv4 && ipv6 && dual_stack_works(),
+        }
+    }
+
+    #[must_use]
+    /// Checks if the provided [`IpVersion`] is supported
+    pub fn supports(&self, version: IpVersion) -> bool {
+        match version {
+            IpVersion::V4 => self.ipv4,
+            IpVersion::V6 => self.ipv6,
+        }
+    }
+}
+
+pub(super) fn ipv4_enabled() -> bool {
+    UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).is_ok()
+}
+
+pub(super) fn ipv6_enabled() -> bool {
+    #[cfg(target_os = "linux")]
+    if !linux_ipv6_enabled() {
+        return false;
+    }
+
+    UdpSocket::bind((Ipv6Addr::LOCALHOST, 0)).is_ok()
+}
+
+#[cfg(target_os = "linux")]
+fn linux_ipv6_enabled() -> bool {
+    if !std::path::Path::new("/proc/net/if_inet6").exists() {
+        return false;
+    }
+
+    std::fs::read_to_string("/proc/sys/net/ipv6/conf/all/disable_ipv6")
+        .map_or(true, |disabled| disabled.trim() != "1")
+}
+
+fn dual_stack_works() -> bool {
+    let Ok(listener) = TcpListener::bind((Ipv6Addr::UNSPECIFIED, 0)) else {
+        return false;
+    };
+    let Ok(addr) = listener.local_addr() else {
+        return false;
+    };
+
+    TcpStream::connect((Ipv4Addr::LOCALHOST, addr.port())).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect() {
+        let support = StackSupport::detect();
+
+        // Every supported platform has IPv4 loopback
+        assert!(support.ipv4);
+        assert!(support.supports(IpVersion::V4));
+        assert_eq!(support.ipv6, IpVersion::V6.is_enabled());
+
+        if support.dual_stack {
+            assert!(support.ipv6);
+        }
+    }
+}
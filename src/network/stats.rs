@@ -0,0 +1,345 @@
+//! Interface traffic statistics
+//!
+//! Counters are read from `/proc/net/dev`, and can be sampled over time to compute transfer rates.
+
+use std::time::Instant;
+
+/// The path to the interface statistics table
+pub const NET_DEV_PATH: &str = "/proc/net/dev";
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when reading interface statistics
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The statistics table could not be read
+    Io(#[from] std::io::Error),
+    #[error("Invalid statistics entry: {0}")]
+    /// An entry in the statistics table could not be parsed
+    InvalidEntry(String),
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
+/// Traffic counters for a single direction
+pub struct Counters {
+    /// The number of bytes transferred
+    pub bytes: u64,
+    /// The number of packets transferred
+    pub packets: u64,
+    /// The number of errors
+    pub errors: u64,
+    /// The number of packets dropped
+    pub dropped: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+/// The traffic counters of a network interface
+pub struct InterfaceStats {
+    /// The name of the interface (i.e `eth0`)
+    pub name: String,
+    /// The received traffic counters
+    pub rx: Counters,
+    /// The transmitted traffic counters
+    pub tx: Counters,
+}
+
+/// Reads the traffic counters of every network interface
+///
+/// # Errors
+/// - The statistics table could not be read
+/// - An entry in the statistics table was invalid
+pub fn interface_stats() -> Result<Vec<InterfaceStats>, Error> {
+    parse(&std::fs::read_to_string(NET_DEV_PATH)?)
+}
+
+fn parse(table: &str) -> Result<Vec<InterfaceStats>, Error> {
+    // The first two lines are headers
+    table
+        .lines()
+        .skip(2)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let invalid = || Error::InvalidEntry(line.to_string());
+
+            let (name, counters) = line.split_once(':').ok_or_else(invalid)?;
+            let counters = counters
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<u64>, _>>()
+                .map_err(|_| invalid())?;
+
+            if counters.len() < 16 {
+                return Err(invalid());
+            }
+
+            Ok(InterfaceStats {
+                name: name.trim().to_string(),
+                rx: Counters {
+                    bytes: counters[0],
+                    packets: counters[1],
+                    errors: counters[2],
+                    dropped: counters[3],
+                },
+                tx: Counters {
+                    bytes: counters[8],
+                    packets: counters[9],
+                    errors: counters[10],
+                    dropped: counters[11],
+                },
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The traffic counters of every interface at a point in time
+pub struct Snapshot {
+    /// When the snapshot was taken
+    pub taken: Instant,
+    /// The counters of each interface
+    pub interfaces: Vec<InterfaceStats>,
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the current traffic counters
+    ///
+    /// # Errors
+    /// - See [`interface_stats`]
+    pub fn take() -> Result<Self, Error> {
+        Ok(Self {
+            taken: Instant::now(),
+            interfaces: interface_stats()?,
+        })
+    }
+
+    #[must_use]
+    /// Computes the rate of traffic on each interface, between an earlier snapshot and this one
+    ///
+    /// Interfaces that are missing from either snapshot are skipped.
+    pub fn rates_since(&self, earlier: &Snapshot) -> Vec<InterfaceRates> {
+        let secs = self
+            .taken
+            .saturating_duration_since(earlier.taken)
+            .as_secs_f64();
+
+        self.interfaces
+            .iter()
+            .filter_map(|current| {
+                let previous = earlier
+                    .interfaces
+                    .iter()
+                    .find(|previous| previous.name == current.name)?;
+
+                Some(InterfaceRates {
+                    name: current.name.clone(),
+                    rx: Rates::between(&previous.rx, &current.rx, secs),
+                    tx: Rates::between(&previous.tx, &current.tx, secs),
+                })
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+/// Traffic rates for a single direction, per second
+pub struct Rates {
+    /// Bytes per second
+    pub bytes: f64,
+    /// Packets per second
+    pub packets: f64,
+    /// Errors per second
+    pub errors: f64,
+    /// Dropped packets per second
+    pub dropped: f64,
+}
+
+impl Rates {
+    #[allow(clippy::cast_precision_loss)]
+    fn between(previous: &Counters, current: &Counters, secs: f64) -> Self {
+        if secs <= 0.0 {
+            return Self::default();
+        }
+
+        let rate = |previous, current| counter_delta(previous, current) as f64 / secs;
+
+        Self {
+            bytes: rate(previous.bytes, current.bytes),
+            packets: rate(previous.packets, current.packets),
+            errors: rate(previous.errors, current.errors),
+            dropped: rate(previous.dropped, current.dropped),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+/// The traffic rates of a network interface
+pub struct InterfaceRates {
+    /// The name of the interface (i.e `eth0`)
+    pub name: String,
+    /// The received traffic rates
+    pub rx: Rates,
+    /// The transmitted traffic rates
+    pub tx: Rates,
+}
+
+/// Computes the difference between two readings of a counter
+///
+/// A counter that went down has either wrapped, or been reset (i.e the interface was re-created).
+/// It is only assumed to have wrapped if the previous reading was in the top quarter of the counter's range, and as
+/// some drivers still report 32 bit counters, that is checked against [`u32::MAX`] before [`u64::MAX`].
+/// Otherwise the counter is assumed to have been reset, and to have counted up from zero since.
+fn counter_delta(previous: u64, current: u64) -> u64 {
+    const fn near_max(value: u64, max: u64) -> bool {
+        value <= max && value > max - max / 4
+    }
+
+    if current >= previous {
+        current - previous
+    } else if near_max(previous, u64::from(u32::MAX)) {
+        // Both readings fit in 32 bits, as the current reading is smaller than the previous one
+        current.wrapping_sub(previous) & u64::from(u32::MAX)
+    } else if near_max(previous, u64::MAX) {
+        current.wrapping_sub(previous)
+    } else {
+        current
+    }
+}
+
+#[derive(Debug, Clone)]
+/// Computes traffic rates between consecutive snapshots
+///
+/// # Examples
+///
+/// ```no_run
+/// # use quork::network::stats::Sampler;
+/// let mut sampler = Sampler::new().unwrap();
+///
+/// loop {
+///     std::thread::sleep(std::time::Duration::from_secs(1));
+///
+///     for iface in sampler.sample().unwrap() {
+///         println!("{}: {:.0} B/s down, {:.0} B/s up", iface.name, iface.rx.bytes, iface.tx.bytes);
+///     }
+/// }
+/// ```
+pub struct Sampler {
+    previous: Snapshot,
+}
+
+impl Sampler {
+    /// Construct a new [`Sampler`], taking the initial snapshot
+    ///
+    /// # Errors
+    /// - See [`interface_stats`]
+    pub fn new() -> Result<Self, Error> {
+        Ok(Self::from_snapshot(Snapshot::take()?))
+    }
+
+    #[must_use]
+    /// Construct a new [`Sampler`] from an existing snapshot
+    pub fn from_snapshot(previous: Snapshot) -> Self {
+        Self { previous }
+    }
+
+    /// Takes a new snapshot, and computes the rates since the previous one
+    ///
+    /// # Errors
+    /// - See [`interface_stats`]
+    pub fn sample(&mut self) -> Result<Vec<InterfaceRates>, Error> {
+        Ok(self.push(Snapshot::take()?))
+    }
+
+    /// Computes the rates between the previous snapshot and the provided one, and replaces the previous snapshot
+    pub fn push(&mut self, snapshot: Snapshot) -> Vec<InterfaceRates> {
+        let rates = snapshot.rates_since(&self.previous);
+        self.previous = snapshot;
+
+        rates
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    const TABLE: &str = "\
+Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:  123456     100    0    0    0     0          0         0   123456     100    0    0    0     0       0          0
+  eth0: 9876543    5000    2    7    0     0          0        12  1234567    4000    1    3    0     0       0          0
+";
+
+    #[test]
+    fn test_parse() {
+        let stats = parse(TABLE).unwrap();
+
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].name, "lo");
+        assert_eq!(stats[1].name, "eth0");
+        assert_eq!(
+            stats[1].rx,
+            Counters {
+                bytes: 9_876_543,
+                packets: 5000,
+                errors: 2,
+                dropped: 7,
+            }
+        );
+        assert_eq!(
+            stats[1].tx,
+            Counters {
+                bytes: 1_234_567,
+                packets: 4000,
+                errors: 1,
+                dropped: 3,
+            }
+        );
+
+        assert!(parse("header\nheader\n  eth0: 1 2 3\n").is_err());
+    }
+
+    #[test]
+    fn test_counter_delta() {
+        assert_eq!(counter_delta(10, 25), 15);
+        assert_eq!(counter_delta(u64::from(u32::MAX) - 4, 5), 10);
+        assert_eq!(counter_delta(u64::MAX - 4, 5), 10);
+
+        // Counters far from either limit were reset, rather than wrapping
+        assert_eq!(counter_delta(1000, 200), 200);
+        assert_eq!(counter_delta(1 << 40, 5), 5);
+    }
+
+    #[test]
+    fn test_rates() {
+        let earlier = Snapshot {
+            taken: Instant::now(),
+            interfaces: parse(TABLE).unwrap(),
+        };
+
+        let mut later = Snapshot {
+            taken: earlier.taken + Duration::from_secs(2),
+            interfaces: earlier.interfaces.clone(),
+        };
+        later.interfaces[1].rx.bytes += 2000;
+        later.interfaces[1].tx.packets += 10;
+        later.interfaces.remove(0);
+
+        let mut sampler = Sampler::from_snapshot(earlier);
+        let rates = sampler.push(later);
+
+        assert_eq!(rates.len(), 1);
+        assert_eq!(rates[0].name, "eth0");
+        assert!((rates[0].rx.bytes - 1000.0).abs() < f64::EPSILON);
+        assert!((rates[0].tx.packets - 5.0).abs() < f64::EPSILON);
+        assert!(rates[0].rx.packets.abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn test_system_stats() {
+        let stats = interface_stats().unwrap();
+
+        assert!(stats.iter().any(|iface| iface.name == "lo"));
+        assert!(Sampler::new().unwrap().sample().is_ok());
+    }
+}
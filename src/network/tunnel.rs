@@ -0,0 +1,362 @@
+//! VPN and tunnel detection
+//!
+//! Tunnel interfaces are identified from `/sys/class/net` on Linux, and by their name on other platforms.
+
+#[cfg(target_os = "linux")]
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use super::interface::Interface;
+#[cfg(target_os = "linux")]
+use super::{policy::RoutingPolicy, route::Route, IpVersion};
+
+/// `ARPHRD_*` hardware types used by tunnel devices
+#[cfg(target_os = "linux")]
+mod arphrd {
+    pub const TUNNEL: u16 = 768;
+    pub const TUNNEL6: u16 = 769;
+    pub const SIT: u16 = 776;
+    pub const IPGRE: u16 = 778;
+    pub const IP6GRE: u16 = 823;
+    pub const NONE: u16 = 65534;
+}
+
+#[cfg(target_os = "linux")]
+const IFF_TAP: u32 = 0x0002;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The kind of tunnel a network interface provides
+pub enum TunnelKind {
+    /// A layer 3 TUN device (i.e `OpenVPN`, `wireguard-go`, `utun` on macOS)
+    Tun,
+    /// A layer 2 TAP device
+    Tap,
+    /// A kernel `WireGuard` interface
+    WireGuard,
+    /// An `IPsec` interface (i.e `xfrm`, `vti` or `ipsec`)
+    Ipsec,
+    /// Any other IP tunnel (i.e GRE, SIT or IPIP)
+    Ip,
+}
+
+/// The information Linux exposes about a network device in `/sys/class/net`
+#[cfg(target_os = "linux")]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+struct SysInfo {
+    arp_type: Option<u16>,
+    tun_flags: Option<u32>,
+    devtype: Option<String>,
+}
+
+#[cfg(target_os = "linux")]
+impl SysInfo {
+    fn read(name: &str) -> Option<Self> {
+        let dir = std::path::Path::new("/sys/class/net").join(name);
+        let read = |file: &str| std::fs::read_to_string(dir.join(file)).ok();
+
+        if !dir.exists() {
+            return None;
+        }
+
+        Some(Self {
+            arp_type: read("type").and_then(|v| v.trim().parse().ok()),
+            tun_flags: read("tun_flags")
+                .and_then(|v| u32::from_str_radix(v.trim().trim_start_matches("0x"), 16).ok()),
+            devtype: read("uevent").and_then(|uevent| {
+                uevent
+                    .lines()
+                    .find_map(|line| line.strip_prefix("DEVTYPE="))
+                    .map(ToString::to_string)
+            }),
+        })
+    }
+}
+
+impl TunnelKind {
+    #[must_use]
+    /// Detects the kind of tunnel the named interface provides
+    ///
+    /// Returns [`None`] if the interface is not a tunnel.
+    pub fn detect(name: &str) -> Option<Self> {
+        cfg_if::cfg_if! {
+            if #[cfg(target_os = "linux")] {
+                Self::classify(name, SysInfo::read(name).as_ref())
+            } else {
+                Self::from_name(name)
+            }
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn classify(name: &str, info: Option<&SysInfo>) -> Option<Self> {
+        if let Some(info) = info {
+            if let Some(flags) = info.tun_flags {
+                return Some(if flags & IFF_TAP == 0 {
+                    TunnelKind::Tun
+                } else {
+                    TunnelKind::Tap
+                });
+            }
+
+            match info.devtype.as_deref() {
+                Some("wireguard") => return Some(TunnelKind::WireGuard),
+                Some("xfrm" | "vti" | "vti6") => return Some(TunnelKind::Ipsec),
+                _ => {}
+            }
+
+            return match info.arp_type {
+                Some(arphrd::TUNNEL | arphrd::TUNNEL6) if name.starts_with("vti") => {
+                    Some(TunnelKind::Ipsec)
+                }
+                Some(
+                    arphrd::TUNNEL | arphrd::TUNNEL6 | arphrd::SIT | arphrd::IPGRE | arphrd::IP6GRE,
+                ) => Some(TunnelKind::Ip),
+                // Devices without a hardware type that were not otherwise identified are still tunnels,
+                // but fall back to the name to work out what kind
+                Some(arphrd::NONE) => Self::from_name(name).or(Some(TunnelKind::Tun)),
+                _ => None,
+            };
+        }
+
+        Self::from_name(name)
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        const PREFIXES: [(&str, TunnelKind); 10] = [
+            ("utun", TunnelKind::Tun),
+            ("tun", TunnelKind::Tun),
+            ("tap", TunnelKind::Tap),
+            ("wg", TunnelKind::WireGuard),
+            ("ipsec", TunnelKind::Ipsec),
+            ("xfrm", TunnelKind::Ipsec),
+            ("vti", TunnelKind::Ipsec),
+            ("gre", TunnelKind::Ip),
+            ("gif", TunnelKind::Ip),
+            ("sit", TunnelKind::Ip),
+        ];
+
+        PREFIXES
+            .iter()
+            .find(|(prefix, _)| name.starts_with(prefix))
+            .map(|(_, kind)| *kind)
+    }
+}
+
+/// Lists the tunnel interfaces on the host
+///
+/// # Errors
+/// - See [`super::interfaces`]
+pub fn tunnels() -> std::io::Result<Vec<Interface>> {
+    Ok(super::interfaces()?
+        .into_iter()
+        .filter(|iface| iface.tunnel.is_some())
+        .collect())
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, thiserror::Error)]
+/// Errors when checking the VPN status
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The interfaces could not be read
+    Io(#[from] std::io::Error),
+    #[error("Route error: {0}")]
+    /// The route table could not be read
+    Route(#[from] super::route::Error),
+}
+
+#[cfg(target_os = "linux")]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The state of any VPNs or tunnels on the host
+pub struct VpnStatus {
+    /// The active tunnel interfaces
+    pub tunnels: Vec<Interface>,
+    /// The routes to the internet that go through one of the tunnels
+    ///
+    /// This includes split routes such as `0.0.0.0/1` and `128.0.0.0/1`, which VPNs use to override the default route,
+    /// and routes in other tables chosen by policy routing rules, which `wg-quick` uses.
+    pub tunneled_routes: Vec<Route>,
+}
+
+#[cfg(target_os = "linux")]
+impl VpnStatus {
+    /// Checks the current VPN status of the host
+    ///
+    /// The routing policy is read over netlink, falling back to only the main routing table if that fails.
+    ///
+    /// # Errors
+    /// - The interfaces or route table could not be read
+    pub fn get() -> Result<Self, Error> {
+        let tunnels: Vec<Interface> = tunnels()?.into_iter().filter(|iface| iface.up).collect();
+        let policy = match RoutingPolicy::get() {
+            Ok(policy) => policy,
+            Err(_) => super::routes()?.into(),
+        };
+
+        Ok(Self::from_parts(tunnels, &policy))
+    }
+
+    #[must_use]
+    /// Works out the VPN status from the tunnel interfaces and the routing policy
+    ///
+    /// Internet traffic is assumed to be unmarked.
+    pub fn from_parts(tunnels: Vec<Interface>, policy: &RoutingPolicy) -> Self {
+        // Addresses in the public internet, used to find the route internet traffic takes
+        const PROBES: [IpAddr; 2] = [
+            IpAddr::V4(Ipv4Addr::new(1, 1, 1, 1)),
+            IpAddr::V6(Ipv6Addr::new(0x2606, 0x4700, 0, 0, 0, 0, 0, 0x1111)),
+        ];
+
+        let tunneled_routes = PROBES
+            .into_iter()
+            .filter_map(|probe| policy.route_to(probe, 0))
+            .filter(|route| tunnels.iter().any(|iface| iface.name == route.interface))
+            .collect();
+
+        Self {
+            tunnels,
+            tunneled_routes,
+        }
+    }
+
+    #[must_use]
+    /// Checks if any tunnel interfaces are active
+    pub fn is_active(&self) -> bool {
+        !self.tunnels.is_empty()
+    }
+
+    #[must_use]
+    /// Checks if internet traffic of the provided [`IpVersion`] goes through a tunnel
+    pub fn is_default_route_tunneled(&self, version: IpVersion) -> bool {
+        self.tunneled_routes
+            .iter()
+            .any(|route| route.ip_version() == version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_by_name() {
+        assert_eq!(TunnelKind::from_name("utun3"), Some(TunnelKind::Tun));
+        assert_eq!(TunnelKind::from_name("wg0"), Some(TunnelKind::WireGuard));
+        assert_eq!(TunnelKind::from_name("ipsec0"), Some(TunnelKind::Ipsec));
+        assert_eq!(TunnelKind::from_name("eth0"), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_classify_sysfs() {
+        let tap = SysInfo {
+            arp_type: Some(1),
+            tun_flags: Some(0x1002),
+            devtype: None,
+        };
+        assert_eq!(
+            TunnelKind::classify("vpn", Some(&tap)),
+            Some(TunnelKind::Tap)
+        );
+
+        let wireguard = SysInfo {
+            arp_type: Some(arphrd::NONE),
+            tun_flags: None,
+            devtype: Some("wireguard".to_string()),
+        };
+        assert_eq!(
+            TunnelKind::classify("corp", Some(&wireguard)),
+            Some(TunnelKind::WireGuard)
+        );
+
+        let gre = SysInfo {
+            arp_type: Some(arphrd::IPGRE),
+            ..SysInfo::default()
+        };
+        assert_eq!(
+            TunnelKind::classify("gre1", Some(&gre)),
+            Some(TunnelKind::Ip)
+        );
+
+        // Names are not trusted when the device is known to be ethernet
+        let ethernet = SysInfo {
+            arp_type: Some(1),
+            ..SysInfo::default()
+        };
+        assert_eq!(TunnelKind::classify("tun0", Some(&ethernet)), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_tunneled_routes() {
+        use crate::network::policy::{Rule, RuleAction, MAIN_TABLE};
+
+        let route = |destination: &str, interface: &str| Route {
+            destination: destination.parse().unwrap(),
+            gateway: None,
+            interface: interface.to_string(),
+            metric: 0,
+        };
+
+        let tunnel = Interface {
+            name: "tun0".to_string(),
+            index: 10,
+            up: true,
+            loopback: false,
+            point_to_point: true,
+            addresses: Vec::new(),
+            tunnel: Some(TunnelKind::Tun),
+        };
+
+        // OpenVPN style split default routes take precedence over the real default route
+        let routes = vec![
+            route("0.0.0.0/0", "eth0"),
+            route("0.0.0.0/1", "tun0"),
+            route("128.0.0.0/1", "tun0"),
+            route("::/0", "eth0"),
+        ];
+
+        let status = VpnStatus::from_parts(vec![tunnel.clone()], &routes.into());
+
+        assert!(status.is_active());
+        assert!(status.is_default_route_tunneled(IpVersion::V4));
+        assert!(!status.is_default_route_tunneled(IpVersion::V6));
+
+        // `wg-quick` style routes in a separate table, chosen by a rule for unmarked traffic
+        let wireguard = Interface {
+            name: "wg0".to_string(),
+            tunnel: Some(TunnelKind::WireGuard),
+            ..tunnel
+        };
+        let policy = RoutingPolicy {
+            rules: vec![Rule {
+                version: IpVersion::V4,
+                priority: 32765,
+                source: None,
+                destination: None,
+                fwmark: 51820,
+                fwmask: u32::MAX,
+                input_interface: None,
+                output_interface: None,
+                invert: true,
+                suppress_prefix_len: None,
+                action: RuleAction::Lookup(51820),
+            }],
+            tables: [
+                (MAIN_TABLE, vec![route("0.0.0.0/0", "eth0")]),
+                (51820, vec![route("0.0.0.0/0", "wg0")]),
+            ]
+            .into(),
+        };
+
+        let status = VpnStatus::from_parts(vec![wireguard], &policy);
+        assert!(status.is_default_route_tunneled(IpVersion::V4));
+        assert_eq!(status.tunneled_routes[0].interface, "wg0");
+    }
+
+    #[test]
+    fn test_system_tunnels() {
+        assert!(tunnels().is_ok());
+    }
+}
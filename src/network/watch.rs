@@ -0,0 +1,71 @@
+//! Watching for network changes
+
+use std::{
+    os::fd::{AsRawFd, OwnedFd},
+    time::Duration,
+};
+
+use nix::sys::{
+    socket::{
+        bind, recv, setsockopt, socket, sockopt, AddressFamily, MsgFlags, NetlinkAddr, SockFlag,
+        SockProtocol, SockType,
+    },
+    time::{TimeVal, TimeValLike},
+};
+
+const RTMGRP_LINK: u32 = 0x1;
+const RTMGRP_IPV4_IFADDR: u32 = 0x10;
+const RTMGRP_IPV4_ROUTE: u32 = 0x40;
+const RTMGRP_IPV6_IFADDR: u32 = 0x100;
+const RTMGRP_IPV6_ROUTE: u32 = 0x400;
+
+#[derive(Debug)]
+/// Listens for link, address and route changes over netlink
+pub(super) struct Watcher(OwnedFd);
+
+impl Watcher {
+    pub(super) fn new() -> Option<Self> {
+        let fd = socket(
+            AddressFamily::Netlink,
+            SockType::Raw,
+            SockFlag::SOCK_CLOEXEC,
+            SockProtocol::NetlinkRoute,
+        )
+        .ok()?;
+
+        let groups = RTMGRP_LINK
+            | RTMGRP_IPV4_IFADDR
+            | RTMGRP_IPV4_ROUTE
+            | RTMGRP_IPV6_IFADDR
+            | RTMGRP_IPV6_ROUTE;
+        bind(fd.as_raw_fd(), &NetlinkAddr::new(0, groups)).ok()?;
+
+        Some(Self(fd))
+    }
+
+    /// Blocks until a change is reported, or the timeout passes
+    pub(super) fn wait(&self, timeout: Duration) {
+        let mut buf = [0u8; 8192];
+
+        // A zero timeout would block forever
+        let micros = i64::try_from(timeout.as_micros())
+            .unwrap_or(i64::MAX)
+            .max(1);
+
+        if setsockopt(
+            &self.0,
+            sockopt::ReceiveTimeout,
+            &TimeVal::microseconds(micros),
+        )
+        .is_err()
+        {
+            std::thread::sleep(timeout);
+            return;
+        }
+
+        // The messages themselves are not needed, as the connectivity is re-read from scratch
+        if recv(self.0.as_raw_fd(), &mut buf, MsgFlags::empty()).is_ok() {
+            while recv(self.0.as_raw_fd(), &mut buf, MsgFlags::MSG_DONTWAIT).is_ok() {}
+        }
+    }
+}
@@ -9,7 +9,7 @@ pub enum Error {
     WindowsError(#[from] windows::core::Error),
 }
 
-#[allow(clippy::module_name_repetitions)]
+#[allow(clippy::module_name_repetitions, clippy::must_use_candidate)]
 /// Checks if the process has root privileges
 ///
 /// # Errors
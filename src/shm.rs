@@ -0,0 +1,701 @@
+//! Typed POSIX shared memory
+//!
+//! A [`SharedMemory`] segment holds a single `T: Copy` value, behind a small header that records the size and alignment
+//! of `T`, so that a segment cannot be opened as a type with a different layout.
+//!
+//! Reads and writes are synchronised with a seqlock kept in the header. Writers take the lock in turn, and readers
+//! retry until they copy out a value that no writer touched in the meantime, so readers never block writers and never
+//! see a torn value, even across processes.
+//!
+//! The header also records the version of its own layout, so that a segment created by an incompatible version of this
+//! module is rejected rather than misread.
+//!
+//! Strings can be stored with [`crate::sized_string::SizedString`] or [`crate::sized_string::ArrayString`], which keep
+//! their bytes inline.
+
+use std::{
+    cell::UnsafeCell,
+    fs::File,
+    mem::MaybeUninit,
+    num::NonZeroUsize,
+    os::{
+        fd::{AsFd, BorrowedFd, OwnedFd},
+        unix::fs::FileExt,
+    },
+    ptr::NonNull,
+    sync::atomic::{fence, AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+use nix::{
+    fcntl::OFlag,
+    sys::{
+        mman::{self, MapFlags, ProtFlags},
+        stat::Mode,
+    },
+};
+
+/// Marks a segment header as fully initialised
+const MAGIC: u64 = u64::from_le_bytes(*b"quorkshm");
+
+/// The version of the segment layout, which must change whenever [`Header`] does
+///
+/// The magic and version always come first, so they can be checked before the rest of the layout.
+const VERSION: u64 = 1;
+
+/// The smallest page size of any supported platform, which bounds the alignment of a mapping
+const MIN_PAGE_SIZE: usize = 4096;
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when creating or opening shared memory
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The shared memory could not be created, opened or mapped
+    Io(#[from] std::io::Error),
+    #[error("The segment is {found} bytes, expected {expected} bytes")]
+    /// The segment is not the size of the value and its header
+    SizeMismatch {
+        /// The size the segment should be
+        expected: u64,
+        /// The size of the segment
+        found: u64,
+    },
+    #[error("The segment holds a value of {found_size} bytes aligned to {found_align}, expected {expected_size} bytes aligned to {expected_align}")]
+    /// The value in the segment has a different layout
+    LayoutMismatch {
+        /// The size of the value
+        expected_size: u64,
+        /// The alignment of the value
+        expected_align: u64,
+        /// The size of the value in the segment
+        found_size: u64,
+        /// The alignment of the value in the segment
+        found_align: u64,
+    },
+    #[error("The value is aligned to {0} bytes, which is more than a page")]
+    /// The value cannot be aligned in a mapping
+    Alignment(usize),
+    #[error("The segment has not been initialised")]
+    /// The segment was not created by [`SharedMemory`], or its creator has not finished initialising it
+    Uninitialised,
+    #[error("The segment has layout version {found}, expected {expected}")]
+    /// The segment was created by an incompatible version of [`SharedMemory`]
+    VersionMismatch {
+        /// The layout version this module uses
+        expected: u64,
+        /// The layout version of the segment
+        found: u64,
+    },
+    #[error("Timed out waiting for a write to finish")]
+    /// A write was in progress for longer than the timeout, which may mean the writer crashed
+    Timeout,
+}
+
+impl From<nix::Error> for Error {
+    fn from(e: nix::Error) -> Self {
+        Self::Io(e.into())
+    }
+}
+
+#[repr(C)]
+struct Header {
+    magic: AtomicU64,
+    version: u64,
+    size: u64,
+    align: u64,
+    seq: AtomicU64,
+}
+
+#[repr(C)]
+struct Segment<T> {
+    header: Header,
+    value: UnsafeCell<T>,
+}
+
+#[derive(Debug)]
+/// A value of type `T` stored in shared memory
+///
+/// `T` should be plain old data, such as integers, arrays and `#[repr(C)]` structs of them.
+/// It must not contain pointers or references, as they are meaningless in other processes.
+///
+/// # Crashes
+///
+/// Writers hold a lock in the segment while they copy the value in. If a writer process dies during a write, the lock
+/// is never released, and [`read`](Self::read), [`write`](Self::write) and [`update`](Self::update) spin forever on
+/// every mapping of the segment. Where another process may crash mid-write, use [`read_timeout`](Self::read_timeout),
+/// [`write_timeout`](Self::write_timeout) and [`update_timeout`](Self::update_timeout), which return
+/// [`Error::Timeout`] instead. The segment cannot be recovered, and must be unlinked and created again.
+///
+/// # Examples
+///
+/// ```
+/// # use quork::{shm::SharedMemory, sized_string::SizedString};
+/// #[derive(Debug, Copy, Clone, PartialEq)]
+/// #[repr(C)]
+/// struct Status {
+///     pid: u32,
+///     name: SizedString<8>,
+/// }
+///
+/// let name = format!("/quork-doc-{}", std::process::id());
+/// let status = Status {
+///     pid: std::process::id(),
+///     name: SizedString::new(*b"starting"),
+/// };
+///
+/// let shm = SharedMemory::create(&name, status).unwrap();
+///
+/// // Usually in another process
+/// // SAFETY: The segment was created with the same type
+/// let other = unsafe { SharedMemory::<Status>::open(&name) }.unwrap();
+/// shm.update(|status| status.name = SizedString::new(*b"running!"));
+/// assert_eq!(other.read().name.as_str(), "running!");
+///
+/// SharedMemory::<Status>::unlink(&name).unwrap();
+/// ```
+pub struct SharedMemory<T: Copy> {
+    segment: NonNull<Segment<T>>,
+    file: File,
+}
+
+// SAFETY: Values are only copied in and out of the segment under the seqlock
+unsafe impl<T: Copy + Send> Send for SharedMemory<T> {}
+// SAFETY: Values are only copied in and out of the segment under the seqlock
+unsafe impl<T: Copy + Send> Sync for SharedMemory<T> {}
+
+impl<T: Copy> SharedMemory<T> {
+    /// The size of the segment, including the header
+    const SEGMENT_SIZE: usize = std::mem::size_of::<Segment<T>>();
+
+    /// Creates a named shared memory segment, holding the provided value
+    ///
+    /// The name should start with a `/`, and contain no other slashes. On Linux it is created in `/dev/shm`.
+    /// The segment is only accessible by the current user, and remains until it is [unlinked](Self::unlink).
+    ///
+    /// # Errors
+    /// - A segment with the name already exists
+    /// - The segment could not be created or mapped
+    /// - `T` is aligned to more than a page
+    pub fn create(name: &str, value: T) -> Result<Self, Error> {
+        let fd = mman::shm_open(
+            name,
+            OFlag::O_RDWR | OFlag::O_CREAT | OFlag::O_EXCL,
+            Mode::S_IRUSR | Mode::S_IWUSR,
+        )?;
+
+        Self::init(fd, value).map_err(|e| {
+            _ = mman::shm_unlink(name);
+            e
+        })
+    }
+
+    /// Opens an existing named shared memory segment
+    ///
+    /// # Errors
+    /// - The segment does not exist, or could not be mapped
+    /// - The segment does not hold a value with the size and alignment of `T`
+    /// - The segment has not been initialised
+    /// - The segment was created with a different layout version
+    ///
+    /// # Safety
+    /// - The segment must have been created for the same type `T`. Only the size and alignment can be checked
+    pub unsafe fn open(name: &str) -> Result<Self, Error> {
+        let fd = mman::shm_open(name, OFlag::O_RDWR, Mode::empty())?;
+
+        Self::from_fd(fd)
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    /// Creates an anonymous shared memory segment, holding the provided value
+    ///
+    /// The segment can be shared with child processes, or by sending the file descriptor from [`AsFd::as_fd`] over a
+    /// Unix socket, and is freed once nothing refers to it.
+    ///
+    /// # Errors
+    /// - The segment could not be created or mapped
+    /// - `T` is aligned to more than a page
+    pub fn anonymous(value: T) -> Result<Self, Error> {
+        use std::ffi::CStr;
+
+        use nix::sys::memfd::{memfd_create, MemFdCreateFlag};
+
+        // SAFETY: The name is NUL terminated, with no other NUL bytes
+        let name = unsafe { CStr::from_bytes_with_nul_unchecked(b"quork-shm\0") };
+        let fd = memfd_create(name, MemFdCreateFlag::MFD_CLOEXEC)?;
+
+        Self::init(fd, value)
+    }
+
+    /// Maps a shared memory segment from a file descriptor, such as one shared from [`SharedMemory::anonymous`]
+    ///
+    /// # Errors
+    /// - The segment could not be mapped
+    /// - The segment does not hold a value with the size and alignment of `T`
+    /// - The segment has not been initialised
+    /// - The segment was created with a different layout version
+    ///
+    /// # Safety
+    /// - The segment must have been created for the same type `T`. Only the size and alignment can be checked
+    pub unsafe fn from_fd(fd: OwnedFd) -> Result<Self, Error> {
+        let file = File::from(fd);
+
+        // Check the version before the size, which depends on the header layout
+        let mut prefix = [0; 16];
+        if file.read_exact_at(&mut prefix, 0).is_ok() {
+            let (magic, version) = prefix.split_at(8);
+
+            let magic = u64::from_ne_bytes(magic.try_into().unwrap_or_default());
+            let version = u64::from_ne_bytes(version.try_into().unwrap_or_default());
+            if magic == MAGIC && version != VERSION {
+                return Err(Error::VersionMismatch {
+                    expected: VERSION,
+                    found: version,
+                });
+            }
+        }
+
+        let found = file.metadata()?.len();
+        let expected = Self::SEGMENT_SIZE as u64;
+        if found != expected {
+            return Err(Error::SizeMismatch { expected, found });
+        }
+
+        let shm = Self::map(file)?;
+        let header = shm.header();
+
+        if header.magic.load(Ordering::Acquire) != MAGIC {
+            return Err(Error::Uninitialised);
+        }
+
+        if header.version != VERSION {
+            return Err(Error::VersionMismatch {
+                expected: VERSION,
+                found: header.version,
+            });
+        }
+
+        let (expected_size, expected_align) = Self::layout();
+        if header.size != expected_size || header.align != expected_align {
+            return Err(Error::LayoutMismatch {
+                expected_size,
+                expected_align,
+                found_size: header.size,
+                found_align: header.align,
+            });
+        }
+
+        Ok(shm)
+    }
+
+    /// Removes a named shared memory segment
+    ///
+    /// Existing mappings remain valid, but the name can no longer be opened.
+    ///
+    /// # Errors
+    /// - The segment does not exist, or could not be removed
+    pub fn unlink(name: &str) -> Result<(), Error> {
+        Ok(mman::shm_unlink(name)?)
+    }
+
+    #[must_use]
+    /// Reads a consistent copy of the value
+    ///
+    /// This spins while a write is in progress, so it only waits as long as a single copy takes.
+    /// If a writer crashed mid-write, this spins forever. See [`SharedMemory::read_timeout`].
+    pub fn read(&self) -> T {
+        loop {
+            if let Some(value) = self.try_read() {
+                return value;
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Reads a consistent copy of the value, spinning for at most the provided timeout
+    ///
+    /// # Errors
+    /// - A write was in progress for the whole timeout, such as when a writer crashed mid-write
+    pub fn read_timeout(&self, timeout: Duration) -> Result<T, Error> {
+        let deadline = Instant::now() + timeout;
+
+        loop {
+            if let Some(value) = self.try_read() {
+                return Ok(value);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+
+    #[must_use]
+    /// Tries to read a consistent copy of the value, without retrying
+    ///
+    /// Returns `None` if a write was in progress.
+    pub fn try_read(&self) -> Option<T> {
+        let seq = &self.header().seq;
+
+        let before = seq.load(Ordering::Acquire);
+        if before & 1 == 1 {
+            return None;
+        }
+
+        // SAFETY: The pointer is valid for the lifetime of the mapping.
+        // The copy may race with a writer, so it is read as possibly invalid bytes and only trusted if the sequence is
+        // unchanged afterwards
+        let value = unsafe { std::ptr::read_volatile(self.value_ptr().cast::<MaybeUninit<T>>()) };
+
+        fence(Ordering::Acquire);
+        if seq.load(Ordering::Relaxed) != before {
+            return None;
+        }
+
+        // SAFETY: No write happened during the copy, so it is a value written in full by a writer
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Replaces the value
+    ///
+    /// If a writer crashed mid-write, this spins forever. See [`SharedMemory::write_timeout`].
+    pub fn write(&self, value: T) {
+        let guard = self.lock();
+
+        self.write_locked(&guard, value);
+    }
+
+    /// Replaces the value, waiting for at most the provided timeout for other writers to finish
+    ///
+    /// # Errors
+    /// - Another write was in progress for the whole timeout, such as when a writer crashed mid-write
+    pub fn write_timeout(&self, value: T, timeout: Duration) -> Result<(), Error> {
+        let guard = self.lock_until(Instant::now() + timeout)?;
+
+        self.write_locked(&guard, value);
+        Ok(())
+    }
+
+    /// Updates the value in place, excluding other writers until the update is done
+    ///
+    /// Readers are not blocked, and see either the old or the new value.
+    /// If a writer crashed mid-write, this spins forever. See [`SharedMemory::update_timeout`].
+    pub fn update(&self, f: impl FnOnce(&mut T)) {
+        let guard = self.lock();
+
+        self.update_locked(&guard, f);
+    }
+
+    /// Updates the value in place, waiting for at most the provided timeout for other writers to finish
+    ///
+    /// # Errors
+    /// - Another write was in progress for the whole timeout, such as when a writer crashed mid-write
+    pub fn update_timeout(&self, f: impl FnOnce(&mut T), timeout: Duration) -> Result<(), Error> {
+        let guard = self.lock_until(Instant::now() + timeout)?;
+
+        self.update_locked(&guard, f);
+        Ok(())
+    }
+
+    fn write_locked(&self, _guard: &WriteGuard<'_>, value: T) {
+        // SAFETY: The pointer is valid for the lifetime of the mapping, and writers are exclusive while locked
+        unsafe { std::ptr::write_volatile(self.value_ptr(), value) };
+    }
+
+    fn update_locked(&self, _guard: &WriteGuard<'_>, f: impl FnOnce(&mut T)) {
+        // SAFETY: The pointer is valid for the lifetime of the mapping, and writers are exclusive while locked
+        let mut value = unsafe { std::ptr::read_volatile(self.value_ptr()) };
+        f(&mut value);
+        // SAFETY: As above
+        unsafe { std::ptr::write_volatile(self.value_ptr(), value) };
+    }
+
+    fn layout() -> (u64, u64) {
+        (
+            std::mem::size_of::<T>() as u64,
+            std::mem::align_of::<T>() as u64,
+        )
+    }
+
+    fn init(fd: OwnedFd, value: T) -> Result<Self, Error> {
+        let file = File::from(fd);
+        file.set_len(Self::SEGMENT_SIZE as u64)?;
+
+        let shm = Self::map(file)?;
+        let (size, align) = Self::layout();
+
+        // SAFETY: The mapping is new, so nothing else is reading it until the magic is set
+        unsafe {
+            let segment = shm.segment.as_ptr();
+            std::ptr::addr_of_mut!((*segment).header.version).write(VERSION);
+            std::ptr::addr_of_mut!((*segment).header.size).write(size);
+            std::ptr::addr_of_mut!((*segment).header.align).write(align);
+            shm.value_ptr().write(value);
+        }
+        shm.header().magic.store(MAGIC, Ordering::Release);
+
+        Ok(shm)
+    }
+
+    fn map(file: File) -> Result<Self, Error> {
+        let align = std::mem::align_of::<Segment<T>>();
+        if align > MIN_PAGE_SIZE {
+            return Err(Error::Alignment(std::mem::align_of::<T>()));
+        }
+
+        let len = NonZeroUsize::new(Self::SEGMENT_SIZE).expect("the segment includes a header");
+
+        // SAFETY: The file is at least the length of the mapping, and mappings are page aligned
+        let ptr = unsafe {
+            mman::mmap(
+                None,
+                len,
+                ProtFlags::PROT_READ | ProtFlags::PROT_WRITE,
+                MapFlags::MAP_SHARED,
+                &file,
+                0,
+            )?
+        };
+
+        Ok(Self {
+            segment: ptr.cast(),
+            file,
+        })
+    }
+
+    fn header(&self) -> &Header {
+        // SAFETY: The header is only accessed through atomics once initialised
+        unsafe { &self.segment.as_ref().header }
+    }
+
+    fn value_ptr(&self) -> *mut T {
+        // SAFETY: The segment is valid for the lifetime of the mapping
+        unsafe { self.segment.as_ref().value.get() }
+    }
+
+    /// Takes the write lock, spinning until it is free
+    fn lock(&self) -> WriteGuard<'_> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return guard;
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+
+    /// Takes the write lock, spinning until it is free or the deadline passes
+    fn lock_until(&self, deadline: Instant) -> Result<WriteGuard<'_>, Error> {
+        loop {
+            if let Some(guard) = self.try_lock() {
+                return Ok(guard);
+            }
+
+            if Instant::now() >= deadline {
+                return Err(Error::Timeout);
+            }
+
+            std::hint::spin_loop();
+        }
+    }
+
+    fn try_lock(&self) -> Option<WriteGuard<'_>> {
+        let seq = &self.header().seq;
+        let current = seq.load(Ordering::Relaxed);
+
+        if current & 1 == 0
+            && seq
+                .compare_exchange_weak(current, current + 1, Ordering::Acquire, Ordering::Relaxed)
+                .is_ok()
+        {
+            fence(Ordering::Release);
+            return Some(WriteGuard { seq });
+        }
+
+        None
+    }
+}
+
+impl<T: Copy> AsFd for SharedMemory<T> {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.file.as_fd()
+    }
+}
+
+impl<T: Copy> Drop for SharedMemory<T> {
+    fn drop(&mut self) {
+        // SAFETY: The mapping was created with this length, and is not used after this
+        _ = unsafe { mman::munmap(self.segment.cast(), Self::SEGMENT_SIZE) };
+    }
+}
+
+/// Releases the seqlock, even if an update panics
+struct WriteGuard<'a> {
+    seq: &'a AtomicU64,
+}
+
+impl Drop for WriteGuard<'_> {
+    fn drop(&mut self) {
+        self.seq.fetch_add(1, Ordering::Release);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use crate::sized_string::{ArrayString, SizedString};
+
+    use super::*;
+
+    fn unique_name() -> String {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        format!(
+            "/quork-test-{}-{}",
+            std::process::id(),
+            COUNTER.fetch_add(1, Ordering::Relaxed)
+        )
+    }
+
+    #[derive(Debug, Copy, Clone, PartialEq)]
+    #[repr(C)]
+    struct Record {
+        id: u64,
+        name: SizedString<8>,
+        label: ArrayString<16>,
+    }
+
+    #[test]
+    fn test_create_open() {
+        let name = unique_name();
+        let record = Record {
+            id: 1,
+            name: SizedString::new(*b"abcdefgh"),
+            label: ArrayString::try_from("first").unwrap(),
+        };
+
+        let shm = SharedMemory::create(&name, record).unwrap();
+        assert!(SharedMemory::create(&name, record).is_err());
+
+        let other = unsafe { SharedMemory::<Record>::open(&name) }.unwrap();
+        assert_eq!(other.read(), record);
+
+        shm.update(|record| {
+            record.id = 2;
+            record.label.clear();
+            record.label.push_str("second").unwrap();
+        });
+        assert_eq!(other.read().id, 2);
+        assert_eq!(other.read().label.as_str(), "second");
+
+        SharedMemory::<Record>::unlink(&name).unwrap();
+        assert!(unsafe { SharedMemory::<Record>::open(&name) }.is_err());
+
+        // Existing mappings are unaffected by unlinking
+        other.write(record);
+        assert_eq!(shm.read(), record);
+    }
+
+    #[test]
+    fn test_layout_mismatch() {
+        let name = unique_name();
+        let _shm = SharedMemory::create(&name, 0u64).unwrap();
+
+        assert!(matches!(
+            unsafe { SharedMemory::<[u64; 2]>::open(&name) },
+            Err(Error::SizeMismatch { .. })
+        ));
+        assert!(matches!(
+            unsafe { SharedMemory::<[u8; 8]>::open(&name) },
+            Err(Error::LayoutMismatch {
+                expected_align: 1,
+                found_align: 8,
+                ..
+            })
+        ));
+
+        SharedMemory::<u64>::unlink(&name).unwrap();
+    }
+
+    #[test]
+    fn test_version_mismatch() {
+        let name = unique_name();
+        let shm = SharedMemory::create(&name, 0u64).unwrap();
+
+        // SAFETY: Nothing else uses the segment, and it is not opened with the version changed
+        unsafe {
+            std::ptr::addr_of_mut!((*shm.segment.as_ptr()).header.version).write(VERSION + 1);
+        };
+
+        assert!(matches!(
+            unsafe { SharedMemory::<u64>::open(&name) },
+            Err(Error::VersionMismatch { expected: VERSION, found }) if found == VERSION + 1
+        ));
+
+        SharedMemory::<u64>::unlink(&name).unwrap();
+    }
+
+    #[test]
+    fn test_crashed_writer() {
+        let name = unique_name();
+        let shm = SharedMemory::create(&name, 1u32).unwrap();
+        SharedMemory::<u32>::unlink(&name).unwrap();
+
+        // A writer that never releases the lock, as if its process died mid-write
+        std::mem::forget(shm.lock());
+
+        let timeout = Duration::from_millis(10);
+        assert!(shm.try_read().is_none());
+        assert!(matches!(shm.read_timeout(timeout), Err(Error::Timeout)));
+        assert!(matches!(shm.write_timeout(2, timeout), Err(Error::Timeout)));
+        assert!(matches!(
+            shm.update_timeout(|value| *value += 1, timeout),
+            Err(Error::Timeout)
+        ));
+    }
+
+    #[test]
+    #[cfg(target_os = "linux")]
+    fn test_anonymous() {
+        let shm = SharedMemory::anonymous([7u32; 4]).unwrap();
+        let fd = shm.as_fd().try_clone_to_owned().unwrap();
+
+        let other = unsafe { SharedMemory::<[u32; 4]>::from_fd(fd) }.unwrap();
+        shm.write([9; 4]);
+        assert_eq!(other.read(), [9; 4]);
+    }
+
+    #[test]
+    fn test_consistent_reads() {
+        let name = unique_name();
+        let shm = SharedMemory::create(&name, [0u64; 64]).unwrap();
+        let reader = unsafe { SharedMemory::<[u64; 64]>::open(&name) }.unwrap();
+        SharedMemory::<[u64; 64]>::unlink(&name).unwrap();
+
+        std::thread::scope(|s| {
+            // Two writers contend for the lock, through a separate mapping to the reader
+            for _ in 0..2 {
+                s.spawn(|| {
+                    for _ in 0..5_000 {
+                        shm.update(|values| {
+                            let next = values[0] + 1;
+                            values.fill(next);
+                        });
+                    }
+                });
+            }
+
+            s.spawn(|| {
+                for _ in 0..20_000 {
+                    let values = reader.read();
+                    assert!(values.iter().all(|v| *v == values[0]), "torn read");
+                }
+            });
+        });
+
+        assert_eq!(reader.read(), [10_000; 64]);
+    }
+}
@@ -3,36 +3,414 @@
 //! This is useful for when you need a string to be stack allocated, but you also need it to be sized (i.e not a reference to a [`str`]).
 //!
 //! Especially when using shared memory this can be useful as the actual string will be stored in shared memory, rather than just the pointer to the string.
+//!
+//! This module works without `std` or `alloc`. Conversions to `String` and `CString` need the `alloc` feature.
+
+mod array;
+mod c_str;
+mod padded;
+#[cfg(feature = "serde")]
+mod serde_impls;
+
+use crate::std::{ops::Deref, slice, str};
 
-use std::ops::Deref;
+pub use array::ArrayString;
+pub use c_str::SizedCStr;
+pub use padded::{LeftJustified, Padded, RightJustified};
+#[cfg(feature = "serde")]
+pub use serde_impls::fixed_bytes;
 
-#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, thiserror::Error)]
+/// Errors when constructing a [`SizedString`]
+pub enum Error {
+    #[error("Expected {expected} bytes, found {found}")]
+    /// The input is not exactly the length of the string
+    LengthMismatch {
+        /// The length of the string
+        expected: usize,
+        /// The length of the input
+        found: usize,
+    },
+    #[error("Capacity of {capacity} bytes exceeded, {required} bytes required")]
+    /// The string does not fit in the capacity
+    CapacityExceeded {
+        /// The capacity of the string
+        capacity: usize,
+        /// The length the string would need to be
+        required: usize,
+    },
+    #[error("Invalid UTF-8 after {valid_up_to} bytes")]
+    /// The input is not valid UTF-8
+    InvalidUtf8 {
+        /// The length of the valid UTF-8 before the first invalid byte
+        valid_up_to: usize,
+    },
+    #[error("No NUL terminator within {capacity} bytes")]
+    /// The input has no NUL terminator within the capacity of a [`SizedCStr`]
+    MissingNul {
+        /// The capacity of the string
+        capacity: usize,
+    },
+    #[error("Interior NUL byte at position {0}")]
+    /// The input contains a NUL byte before its end
+    InteriorNul(usize),
+}
+
+#[derive(Copy, Clone)]
+#[repr(transparent)]
 /// A sized, stack allocated string type.
 ///
 /// This is useful for when you need a string to be stack allocated, but you also need it to be sized (i.e not a reference to a [`str`]).
 ///
 /// Especially when using shared memory this can be useful as the actual string will be stored in shared memory, rather than just the pointer to the string.
+///
+/// The bytes are always valid UTF-8.
+///
+/// # Padding
+///
+/// Strings shorter than `N` are padded with trailing NUL bytes, as in a C string buffer. The padding is part of the
+/// string's contents, but not of its value:
+///
+/// - [`as_str`](Self::as_str), [`as_bytes`](Self::as_bytes), [`Deref`] (and so `len`) and
+///   [`Debug`](crate::std::fmt::Debug) include the padding, so they show exactly what is stored.
+/// - [`trimmed`](Self::trimmed), `==`, ordering, hashing, [`Borrow<str>`](crate::std::borrow::Borrow),
+///   [`Display`](crate::std::fmt::Display), conversion to `String` and serde ignore it, so `"abc"` padded to 8 bytes
+///   equals `"abc"` in any capacity, and can be looked up in a map by `"abc"`.
+///
+/// Parsing or converting from a [`str`](prim@str) pads the string, while [`SizedString::try_from_bytes`] takes the
+/// whole buffer, padding included, and so needs exactly `N` bytes.
+///
+/// ```
+/// # use quork::sized_string::SizedString;
+/// let s: SizedString<8> = "abc".parse().unwrap();
+///
+/// assert_eq!(s.as_str(), "abc\0\0\0\0\0");
+/// assert_eq!(s.len(), 8);
+/// assert_eq!(s.trimmed(), "abc");
+/// assert_eq!(s, "abc");
+/// assert_eq!(s.to_string(), "abc");
+/// ```
 pub struct SizedString<const N: usize>([u8; N]);
 
 impl<const N: usize> SizedString<N> {
     #[must_use]
     /// Construct a new [`SizedString`] from a byte array
+    ///
+    /// # Panics
+    /// - If the bytes are not valid UTF-8. Use [`SizedString::try_from_bytes`] for runtime data
     pub const fn new(bytes: [u8; N]) -> Self {
+        assert!(
+            validate_utf8(&bytes).is_ok(),
+            "SizedString bytes must be valid UTF-8"
+        );
+
         Self(bytes)
     }
 
     #[must_use]
-    /// Get the string as a [`str`]
+    /// Construct a new [`SizedString`] from a byte array, without checking that it is valid UTF-8
+    ///
+    /// # Safety
+    /// - The bytes must be valid UTF-8
+    pub const unsafe fn new_unchecked(bytes: [u8; N]) -> Self {
+        Self(bytes)
+    }
+
+    /// Construct a new [`SizedString`] from a byte slice
+    ///
+    /// # Errors
+    /// - The slice is not exactly `N` bytes long
+    /// - The slice is not valid UTF-8
+    pub const fn try_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        if bytes.len() != N {
+            return Err(Error::LengthMismatch {
+                expected: N,
+                found: bytes.len(),
+            });
+        }
+
+        if let Err(valid_up_to) = validate_utf8(bytes) {
+            return Err(Error::InvalidUtf8 { valid_up_to });
+        }
+
+        let mut array = [0; N];
+        let mut i = 0;
+        while i < N {
+            array[i] = bytes[i];
+            i += 1;
+        }
+
+        Ok(Self(array))
+    }
+
+    #[must_use]
+    /// Get the string as a [`str`], including its trailing NUL padding
     pub const fn as_str(&self) -> &str {
-        unsafe { std::str::from_utf8_unchecked(&self.0) }
+        // SAFETY: Every constructor ensures the bytes are valid UTF-8
+        unsafe { str::from_utf8_unchecked(&self.0) }
+    }
+
+    #[must_use]
+    /// Get the string as a [`str`], without its trailing NUL padding
+    pub const fn trimmed(&self) -> &str {
+        let mut len = N;
+        while len > 0 && self.0[len - 1] == 0 {
+            len -= 1;
+        }
+
+        // SAFETY: NUL is a single byte character, so the bytes up to `len` are still valid UTF-8
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.0.as_ptr(), len)) }
+    }
+
+    #[must_use]
+    /// Get the underlying bytes, including the trailing NUL padding
+    pub const fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+
+    #[must_use]
+    /// Construct a new [`SizedString`] by concatenating byte slices, such as from [`str::as_bytes`]
+    ///
+    /// This is used by [`concat_sized!`](crate::concat_sized), which works out `N` from the parts.
+    ///
+    /// # Panics
+    /// - If the parts are not exactly `N` bytes long in total
+    /// - If the concatenated bytes are not valid UTF-8
+    pub const fn from_parts(parts: &[&[u8]]) -> Self {
+        let mut bytes = [0; N];
+        let mut len = 0;
+
+        let mut part = 0;
+        while part < parts.len() {
+            let mut i = 0;
+            while i < parts[part].len() {
+                assert!(len < N, "SizedString parts are longer than the capacity");
+                bytes[len] = parts[part][i];
+                len += 1;
+                i += 1;
+            }
+            part += 1;
+        }
+
+        assert!(len == N, "SizedString parts are shorter than the capacity");
+
+        Self::new(bytes)
+    }
+
+    #[must_use]
+    /// Concatenates two strings, including any NUL padding
+    ///
+    /// `O` must be `N + M`, which is usually inferred from the type being assigned to.
+    ///
+    /// # Panics
+    /// - If `O` is not `N + M`. In a const context, this fails to compile
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use quork::sized_string::SizedString;
+    /// const PREFIX: SizedString<4> = SizedString::new(*b"app-");
+    /// const NAME: SizedString<9> = PREFIX.concat(&SizedString::new(*b"quork"));
+    ///
+    /// assert_eq!(NAME, "app-quork");
+    /// ```
+    pub const fn concat<const M: usize, const O: usize>(
+        &self,
+        other: &SizedString<M>,
+    ) -> SizedString<O> {
+        assert!(
+            O == N + M,
+            "concatenated length must be the sum of both lengths"
+        );
+
+        let mut bytes = [0; O];
+        let mut i = 0;
+        while i < N {
+            bytes[i] = self.0[i];
+            i += 1;
+        }
+        while i < O {
+            bytes[i] = other.0[i - N];
+            i += 1;
+        }
+
+        SizedString(bytes)
+    }
+
+    #[must_use]
+    /// Copies `M` bytes of the string, starting at the provided byte index
+    ///
+    /// # Panics
+    /// - If the range is out of bounds
+    /// - If either end of the range does not lie on a character boundary
+    pub const fn slice<const M: usize>(&self, start: usize) -> SizedString<M> {
+        let end = start + M;
+        assert!(end <= N, "slice is out of bounds");
+        assert!(
+            is_char_boundary(&self.0, start) && is_char_boundary(&self.0, end),
+            "slice must lie on character boundaries"
+        );
+
+        let mut bytes = [0; M];
+        let mut i = 0;
+        while i < M {
+            bytes[i] = self.0[start + i];
+            i += 1;
+        }
+
+        SizedString(bytes)
+    }
+
+    #[must_use]
+    /// Checks if two strings are equal, ignoring their NUL padding, in a const context
+    ///
+    /// This is the same as `==`, which cannot be used in a const context.
+    pub const fn const_eq<const M: usize>(&self, other: &SizedString<M>) -> bool {
+        self.const_eq_str(other.trimmed())
+    }
+
+    #[must_use]
+    /// Checks if the string is equal to a [`str`], ignoring its NUL padding, in a const context
+    pub const fn const_eq_str(&self, other: &str) -> bool {
+        let a = self.trimmed().as_bytes();
+        let b = other.as_bytes();
+
+        if a.len() != b.len() {
+            return false;
+        }
+
+        let mut i = 0;
+        while i < a.len() {
+            if a[i] != b[i] {
+                return false;
+            }
+            i += 1;
+        }
+
+        true
+    }
+
+    #[must_use]
+    /// Returns a copy of the string with every ASCII letter in upper case
+    pub const fn to_ascii_uppercase(&self) -> Self {
+        let mut bytes = self.0;
+        let mut i = 0;
+        while i < N {
+            bytes[i] = bytes[i].to_ascii_uppercase();
+            i += 1;
+        }
+
+        Self(bytes)
+    }
+
+    #[must_use]
+    /// Returns a copy of the string with every ASCII letter in lower case
+    pub const fn to_ascii_lowercase(&self) -> Self {
+        let mut bytes = self.0;
+        let mut i = 0;
+        while i < N {
+            bytes[i] = bytes[i].to_ascii_lowercase();
+            i += 1;
+        }
+
+        Self(bytes)
+    }
+}
+
+impl From<str::Utf8Error> for Error {
+    fn from(e: str::Utf8Error) -> Self {
+        Error::InvalidUtf8 {
+            valid_up_to: e.valid_up_to(),
+        }
     }
 }
 
+/// Checks that the bytes are valid UTF-8, with the same rules as [`str::from_utf8`]
+///
+/// [`str::from_utf8`] can only be used in a const context since Rust 1.87, which is newer than the MSRV.
+/// Returns the length of the valid UTF-8 before the first invalid byte on failure.
+const fn validate_utf8(bytes: &[u8]) -> Result<(), usize> {
+    let mut i = 0;
+
+    while i < bytes.len() {
+        // The width of the character, and the range of its second byte, which excludes overlong encodings,
+        // surrogates, and characters above `U+10FFFF`
+        let (width, low, high) = match bytes[i] {
+            0x00..=0x7f => {
+                i += 1;
+                continue;
+            }
+            0xc2..=0xdf => (2, 0x80, 0xbf),
+            0xe0 => (3, 0xa0, 0xbf),
+            0xe1..=0xec | 0xee..=0xef => (3, 0x80, 0xbf),
+            0xed => (3, 0x80, 0x9f),
+            0xf0 => (4, 0x90, 0xbf),
+            0xf1..=0xf3 => (4, 0x80, 0xbf),
+            0xf4 => (4, 0x80, 0x8f),
+            _ => return Err(i),
+        };
+
+        if i + width > bytes.len() || bytes[i + 1] < low || bytes[i + 1] > high {
+            return Err(i);
+        }
+
+        let mut j = 2;
+        while j < width {
+            if bytes[i + j] & 0xc0 != 0x80 {
+                return Err(i);
+            }
+            j += 1;
+        }
+
+        i += width;
+    }
+
+    Ok(())
+}
+
+const fn is_char_boundary(bytes: &[u8], idx: usize) -> bool {
+    // Continuation bytes are `0b10xx_xxxx`
+    idx == bytes.len() || bytes[idx] & 0xc0 != 0x80
+}
+
+#[macro_export]
+/// Concatenates [`SizedString`]s and `&str`s into a [`SizedString`], working out its length
+///
+/// Each part must be usable in a const context, such as a `const` item or a literal, as its length is needed for
+/// the type.
+///
+/// # Examples
+///
+/// ```
+/// # use quork::{concat_sized, sized_string::SizedString};
+/// const APP: SizedString<5> = SizedString::new(*b"quork");
+/// const VERSION: &str = "1.0";
+///
+/// const ID: SizedString<11> = concat_sized!(APP.to_ascii_uppercase(), "/v", VERSION, ".");
+/// assert_eq!(ID, "QUORK/v1.0.");
+/// ```
+macro_rules! concat_sized {
+    ($($part:expr),+ $(,)?) => {
+        $crate::sized_string::SizedString::<{ 0 $(+ $part.as_bytes().len())+ }>::from_parts(
+            &[$($part.as_bytes() as &[u8]),+]
+        )
+    };
+}
+
 impl<const N: usize> Deref for SizedString<N> {
     type Target = str;
 
     fn deref(&self) -> &Self::Target {
-        unsafe { std::str::from_utf8_unchecked(&self.0) }
+        self.as_str()
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for SizedString<N> {
+    type Error = Error;
+
+    /// Converts a string of up to `N` bytes, padding it with NUL bytes, in the same way as [`str::parse`]
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
     }
 }
 
@@ -44,7 +422,7 @@ impl<const N: usize> AsRef<str> for SizedString<N> {
 
 impl<const N: usize, T> AsRef<T> for SizedString<N>
 where
-    str: std::convert::AsRef<T>,
+    str: AsRef<T>,
 {
     fn as_ref(&self) -> &T {
         let s: &str = self.as_ref();
@@ -53,20 +431,154 @@ where
 }
 
 mod string_trait_impls {
-    use std::fmt::Debug;
+    use crate::std::{
+        borrow::Borrow,
+        cmp::Ordering,
+        fmt::{self, Debug, Display},
+        hash::{Hash, Hasher},
+        str::FromStr,
+    };
 
-    use super::SizedString;
+    #[cfg(feature = "alloc")]
+    use alloc::string::String;
+
+    use super::{Error, SizedString};
 
     impl<const N: usize> Debug for SizedString<N> {
-        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             f.debug_tuple("SizedString").field(&self.as_str()).finish()
         }
     }
+
+    impl<const N: usize> Display for SizedString<N> {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.pad(self.trimmed())
+        }
+    }
+
+    impl<const N: usize> Default for SizedString<N> {
+        fn default() -> Self {
+            Self([0; N])
+        }
+    }
+
+    impl<const N: usize> FromStr for SizedString<N> {
+        type Err = Error;
+
+        /// Parses a string of up to `N` bytes, padding it with NUL bytes
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            if s.len() > N {
+                return Err(Error::CapacityExceeded {
+                    capacity: N,
+                    required: s.len(),
+                });
+            }
+
+            let mut bytes = [0; N];
+            bytes[..s.len()].copy_from_slice(s.as_bytes());
+
+            Ok(Self(bytes))
+        }
+    }
+
+    #[cfg(feature = "alloc")]
+    impl<const N: usize> From<SizedString<N>> for String {
+        fn from(s: SizedString<N>) -> Self {
+            s.trimmed().into()
+        }
+    }
+
+    impl<const N: usize> Borrow<str> for SizedString<N> {
+        fn borrow(&self) -> &str {
+            self.trimmed()
+        }
+    }
+
+    impl<const N: usize> Hash for SizedString<N> {
+        fn hash<H: Hasher>(&self, state: &mut H) {
+            // Must match the hash of the borrowed `str`
+            self.trimmed().hash(state);
+        }
+    }
+
+    impl<const N: usize, const M: usize> PartialEq<SizedString<M>> for SizedString<N> {
+        fn eq(&self, other: &SizedString<M>) -> bool {
+            self.trimmed() == other.trimmed()
+        }
+    }
+
+    impl<const N: usize> Eq for SizedString<N> {}
+
+    impl<const N: usize, const M: usize> PartialOrd<SizedString<M>> for SizedString<N> {
+        fn partial_cmp(&self, other: &SizedString<M>) -> Option<Ordering> {
+            Some(self.trimmed().cmp(other.trimmed()))
+        }
+    }
+
+    impl<const N: usize> Ord for SizedString<N> {
+        fn cmp(&self, other: &Self) -> Ordering {
+            self.trimmed().cmp(other.trimmed())
+        }
+    }
+
+    macro_rules! impl_str_eq {
+        ($($ty:ty),+) => {
+            $(
+                impl<const N: usize> PartialEq<$ty> for SizedString<N> {
+                    fn eq(&self, other: &$ty) -> bool {
+                        self.trimmed() == &other[..]
+                    }
+                }
+
+                impl<const N: usize> PartialEq<SizedString<N>> for $ty {
+                    fn eq(&self, other: &SizedString<N>) -> bool {
+                        &self[..] == other.trimmed()
+                    }
+                }
+            )+
+        };
+    }
+
+    impl_str_eq!(str, &str);
+    #[cfg(feature = "alloc")]
+    impl_str_eq!(String);
 }
 
 #[cfg(test)]
 mod tests {
 
+    #[test]
+    fn test_validate_utf8() {
+        let check = |bytes: &[u8]| {
+            assert_eq!(
+                super::validate_utf8(bytes),
+                str::from_utf8(bytes)
+                    .map(|_| ())
+                    .map_err(|e| e.valid_up_to()),
+                "{bytes:x?}"
+            );
+        };
+
+        let tails = [0x00, 0x7f, 0x80, 0x8f, 0x90, 0x9f, 0xa0, 0xbf, 0xc0, 0xff];
+
+        for first in 0..=u8::MAX {
+            check(&[first]);
+
+            for second in 0..=u8::MAX {
+                check(&[first, second]);
+                check(&[b'a', first, second]);
+
+                for third in tails {
+                    check(&[first, second, third]);
+
+                    for fourth in tails {
+                        check(&[first, second, third, fourth]);
+                    }
+                }
+            }
+        }
+    }
+
     #[test]
     fn test_sized_string() {
         let s = quork_proc::sized_string!("hello world");
@@ -76,4 +588,115 @@ mod tests {
         assert_eq!(s.len(), 11);
         assert_eq!(s_str, "hello world");
     }
+
+    #[test]
+    fn test_checked_constructors() {
+        use super::{Error, SizedString};
+
+        let s = SizedString::<5>::try_from("héll").unwrap();
+        assert_eq!(s.as_str(), "héll");
+        assert_eq!(s.as_bytes(), "héll".as_bytes());
+
+        // Converting from a `str` pads it, in the same way as parsing
+        let s = SizedString::<5>::try_from("hé").unwrap();
+        assert_eq!(s.as_bytes(), b"h\xc3\xa9\0\0");
+        assert_eq!(Ok(s), "hé".parse());
+
+        assert_eq!(
+            SizedString::<5>::try_from("hello!"),
+            Err(Error::CapacityExceeded {
+                capacity: 5,
+                required: 6
+            })
+        );
+        assert_eq!(
+            SizedString::<5>::try_from_bytes("hé".as_bytes()),
+            Err(Error::LengthMismatch {
+                expected: 5,
+                found: 3
+            })
+        );
+        assert!(matches!(
+            SizedString::<2>::try_from_bytes(&[0xc3, 0x28]),
+            Err(Error::InvalidUtf8 { valid_up_to: 0 })
+        ));
+
+        // SAFETY: The bytes are ASCII
+        let s = unsafe { SizedString::new_unchecked(*b"abc") };
+        assert_eq!(s.as_str(), "abc");
+    }
+
+    #[test]
+    fn test_traits() {
+        use std::collections::HashMap;
+
+        use super::{Error, SizedString};
+
+        let padded: SizedString<8> = "abc".parse().unwrap();
+        let exact = SizedString::new(*b"abc");
+
+        assert_eq!(padded.as_bytes(), b"abc\0\0\0\0\0");
+        assert_eq!(padded, exact);
+        assert_eq!(padded, "abc");
+        assert_eq!("abc", padded);
+        assert_ne!(padded, "abc\0");
+        assert!(exact < SizedString::new(*b"abd"));
+        assert!(padded < SizedString::new(*b"b"));
+
+        assert_eq!(padded.to_string(), "abc");
+        assert_eq!(format!("[{padded:>5}]"), "[  abc]");
+        #[cfg(feature = "alloc")]
+        {
+            assert_eq!(padded, String::from("abc"));
+            assert_eq!(String::from(padded), "abc");
+        }
+        assert_eq!(SizedString::<4>::default(), "");
+
+        assert_eq!(
+            "too long".parse::<SizedString<4>>(),
+            Err(Error::CapacityExceeded {
+                capacity: 4,
+                required: 8
+            })
+        );
+
+        let mut map = HashMap::new();
+        map.insert(padded, 1);
+        assert_eq!(map.get("abc"), Some(&1));
+    }
+
+    #[test]
+    fn test_const_ops() {
+        use super::SizedString;
+
+        const HELLO: SizedString<5> = SizedString::new(*b"hello");
+        const WORLD: SizedString<7> = SizedString::new(*b" w\xc3\xb6rld");
+        const GREETING: SizedString<12> = HELLO.concat(&WORLD);
+        const SHOUT: SizedString<12> = GREETING.to_ascii_uppercase();
+        const SLICE: SizedString<4> = GREETING.slice(7);
+        const JOINED: SizedString<18> = crate::concat_sized!(SHOUT, "-", HELLO);
+        const _: () = assert!(HELLO.const_eq(&GREETING.slice::<5>(0)));
+        const _: () = assert!(SizedString::new(*b"hello\0\0").const_eq(&HELLO));
+
+        assert_eq!(GREETING, "hello wörld");
+        assert_eq!(SHOUT, "HELLO WöRLD");
+        assert_eq!(SHOUT.to_ascii_lowercase(), GREETING);
+        assert_eq!(SLICE, "örl");
+        assert_eq!(JOINED, "HELLO WöRLD-hello");
+
+        assert!(HELLO.const_eq_str("hello"));
+        assert!(!HELLO.const_eq_str("help!"));
+    }
+
+    #[test]
+    #[should_panic = "character boundaries"]
+    fn test_slice_boundary() {
+        let _ = super::SizedString::new(*b"w\xc3\xb6rld").slice::<2>(2);
+    }
+
+    #[test]
+    #[should_panic = "valid UTF-8"]
+    fn test_new_invalid() {
+        let _ = super::SizedString::new([0xff, 0xfe]);
+    }
 }
@@ -0,0 +1,382 @@
+//! A length-tracked string with a fixed inline capacity
+
+use crate::std::{
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    slice, str,
+};
+
+use super::{Error, SizedString};
+
+#[derive(Copy, Clone)]
+#[repr(C)]
+/// A stack allocated string with a capacity of `N` bytes, that tracks its own length
+///
+/// Unlike [`SizedString`], this does not need to be filled to exactly `N` bytes, so it can be built from runtime data.
+/// It has a fixed layout, with the length stored as a `u32` before the bytes, so it can be stored in shared memory in
+/// the same way, including between 32 and 64 bit processes. `N` must fit in a `u32`.
+///
+/// Writing with [`write!`] is all or nothing, so a write that does not fit leaves the string as it was.
+///
+/// # Examples
+///
+/// ```
+/// # use quork::sized_string::ArrayString;
+/// use std::fmt::Write;
+///
+/// let mut s = ArrayString::<16>::new();
+/// write!(s, "{}-{}", "abc", 123).unwrap();
+///
+/// assert_eq!(s.as_str(), "abc-123");
+/// assert_eq!(s.remaining_capacity(), 9);
+/// ```
+pub struct ArrayString<const N: usize> {
+    len: u32,
+    bytes: [u8; N],
+}
+
+impl<const N: usize> ArrayString<N> {
+    /// Fails to compile if the capacity does not fit in the stored length
+    const CAPACITY_FITS: () = assert!(
+        N <= u32::MAX as usize,
+        "the capacity of an ArrayString must fit in a u32"
+    );
+
+    #[must_use]
+    /// Construct a new, empty [`ArrayString`]
+    pub const fn new() -> Self {
+        let () = Self::CAPACITY_FITS;
+
+        Self {
+            len: 0,
+            bytes: [0; N],
+        }
+    }
+
+    #[must_use]
+    /// Get the string as a [`str`]
+    pub const fn as_str(&self) -> &str {
+        // SAFETY: `len` never exceeds `N`, and the bytes up to `len` are always valid UTF-8
+        unsafe { str::from_utf8_unchecked(slice::from_raw_parts(self.bytes.as_ptr(), self.len())) }
+    }
+
+    #[must_use]
+    /// The length of the string, in bytes
+    pub const fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    #[must_use]
+    /// Checks if the string is empty
+    pub const fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn set_len(&mut self, len: usize) {
+        // The length never exceeds `N`, which fits in a `u32`
+        #[allow(clippy::cast_possible_truncation)]
+        {
+            self.len = len as u32;
+        }
+    }
+
+    #[must_use]
+    /// The maximum length of the string, in bytes
+    pub const fn capacity(&self) -> usize {
+        N
+    }
+
+    #[must_use]
+    /// The number of bytes that can be pushed before the string is full
+    pub const fn remaining_capacity(&self) -> usize {
+        N - self.len()
+    }
+
+    /// Appends a character to the end of the string
+    ///
+    /// # Errors
+    /// - The character does not fit in the remaining capacity
+    pub fn push(&mut self, c: char) -> Result<(), Error> {
+        self.push_str(c.encode_utf8(&mut [0; 4]))
+    }
+
+    /// Appends a string to the end of the string
+    ///
+    /// # Errors
+    /// - The string does not fit in the remaining capacity, in which case nothing is appended
+    pub fn push_str(&mut self, s: &str) -> Result<(), Error> {
+        if s.len() > self.remaining_capacity() {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: self.len() + s.len(),
+            });
+        }
+
+        let len = self.len();
+        self.bytes[len..len + s.len()].copy_from_slice(s.as_bytes());
+        self.set_len(len + s.len());
+
+        Ok(())
+    }
+
+    /// Appends as much of a string as fits in the remaining capacity, without splitting a character
+    ///
+    /// Returns the number of bytes appended
+    pub fn push_str_truncated(&mut self, s: &str) -> usize {
+        let mut end = s.len().min(self.remaining_capacity());
+        while !s.is_char_boundary(end) {
+            end -= 1;
+        }
+
+        // This always fits, as it was truncated to the remaining capacity
+        _ = self.push_str(&s[..end]);
+
+        end
+    }
+
+    /// Removes the last character from the string, and returns it
+    pub fn pop(&mut self) -> Option<char> {
+        let c = self.as_str().chars().next_back()?;
+        self.truncate(self.len() - c.len_utf8());
+
+        Some(c)
+    }
+
+    /// Shortens the string to the provided length, in bytes
+    ///
+    /// Does nothing if the length is greater than the current length
+    ///
+    /// # Panics
+    /// - If the length does not lie on a character boundary
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len() {
+            return;
+        }
+
+        assert!(
+            self.as_str().is_char_boundary(len),
+            "new length must lie on a character boundary"
+        );
+
+        // Unused bytes are kept zeroed, so the layout is deterministic when shared
+        let old_len = self.len();
+        self.bytes[len..old_len].fill(0);
+        self.set_len(len);
+    }
+
+    /// Removes every character from the string
+    pub fn clear(&mut self) {
+        self.truncate(0);
+    }
+
+    /// Inserts a character at the provided byte index
+    ///
+    /// # Errors
+    /// - The character does not fit in the remaining capacity
+    ///
+    /// # Panics
+    /// - If the index is greater than the length, or does not lie on a character boundary
+    pub fn insert(&mut self, idx: usize, c: char) -> Result<(), Error> {
+        assert!(
+            self.as_str().is_char_boundary(idx),
+            "index must lie on a character boundary"
+        );
+
+        let mut buf = [0; 4];
+        let encoded = c.encode_utf8(&mut buf).as_bytes();
+
+        if encoded.len() > self.remaining_capacity() {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: self.len() + encoded.len(),
+            });
+        }
+
+        let len = self.len();
+        self.bytes.copy_within(idx..len, idx + encoded.len());
+        self.bytes[idx..idx + encoded.len()].copy_from_slice(encoded);
+        self.set_len(len + encoded.len());
+
+        Ok(())
+    }
+}
+
+impl<const N: usize> Default for ArrayString<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for ArrayString<N> {
+    type Target = str;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_str()
+    }
+}
+
+impl<const N: usize> AsRef<str> for ArrayString<N> {
+    fn as_ref(&self) -> &str {
+        self
+    }
+}
+
+impl<const N: usize> PartialEq for ArrayString<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl<const N: usize> Eq for ArrayString<N> {}
+
+impl<const N: usize> Hash for ArrayString<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state);
+    }
+}
+
+impl<const N: usize> fmt::Debug for ArrayString<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("ArrayString").field(&self.as_str()).finish()
+    }
+}
+
+impl<const N: usize> fmt::Write for ArrayString<N> {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.push_str(s).map_err(|_| fmt::Error)
+    }
+
+    /// Writes the formatted string, or nothing if it does not fit
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> fmt::Result {
+        let len = self.len();
+
+        // Earlier pieces may have fit before a later one failed, so roll them back
+        fmt::write(self, args).map_err(|e| {
+            self.truncate(len);
+            e
+        })
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for ArrayString<N> {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        let mut string = Self::new();
+        string.push_str(s)?;
+
+        Ok(string)
+    }
+}
+
+impl<const N: usize> From<SizedString<N>> for ArrayString<N> {
+    /// Converts a [`SizedString`], without its trailing NUL padding
+    fn from(s: SizedString<N>) -> Self {
+        let mut string = Self::new();
+        string.bytes = *s.as_bytes();
+        string.set_len(s.trimmed().len());
+
+        string
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fmt::Write;
+
+    use super::*;
+
+    #[test]
+    fn test_push_pop() {
+        let mut s = ArrayString::<8>::new();
+        assert!(s.is_empty());
+
+        s.push('h').unwrap();
+        s.push_str("éllo").unwrap();
+        assert_eq!(s.as_str(), "héllo");
+        assert_eq!(s.len(), 6);
+
+        assert_eq!(
+            s.push_str("!!!"),
+            Err(Error::CapacityExceeded {
+                capacity: 8,
+                required: 9
+            })
+        );
+        assert_eq!(s.as_str(), "héllo");
+
+        assert_eq!(s.pop(), Some('o'));
+        assert_eq!(s.pop(), Some('l'));
+        s.truncate(3);
+        assert_eq!(s.as_str(), "hé");
+        assert_eq!(s.pop(), Some('é'));
+
+        s.clear();
+        assert_eq!(s.pop(), None);
+        assert_eq!(s, ArrayString::new());
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut s = ArrayString::<8>::try_from("hllo").unwrap();
+
+        s.insert(1, 'é').unwrap();
+        s.insert(0, '>').unwrap();
+        assert_eq!(s.as_str(), ">héllo");
+        assert!(s.insert(0, '€').is_err());
+        assert_eq!(s.as_str(), ">héllo");
+    }
+
+    #[test]
+    #[should_panic = "character boundary"]
+    fn test_truncate_boundary() {
+        let mut s = ArrayString::<8>::try_from("é").unwrap();
+        s.truncate(1);
+    }
+
+    #[test]
+    fn test_truncated_and_write() {
+        let mut s = ArrayString::<5>::new();
+
+        // Cutting at 5 bytes would split the `é`
+        assert_eq!(s.push_str_truncated("abcdé"), 4);
+        assert_eq!(s.as_str(), "abcd");
+
+        assert!(write!(s, "{}", 12345).is_err());
+        assert_eq!(s.as_str(), "abcd");
+
+        // The first piece fits, but is rolled back when the second does not
+        s.clear();
+        assert!(write!(s, "ab{}", 1234).is_err());
+        assert_eq!(s.as_str(), "");
+
+        let mut s = ArrayString::<16>::new();
+        let name = "port";
+        write!(s, "{name}:{}", 8080).unwrap();
+        assert_eq!(s.as_str(), "port:8080");
+
+        // Copies are independent
+        let copy = s;
+        s.clear();
+        assert_eq!(copy.as_str(), "port:8080");
+    }
+
+    #[test]
+    fn test_layout() {
+        // The same on 32 and 64 bit targets
+        assert_eq!(std::mem::size_of::<ArrayString<12>>(), 16);
+        assert_eq!(std::mem::align_of::<ArrayString<12>>(), 4);
+    }
+
+    #[test]
+    fn test_from_sized() {
+        let s = ArrayString::from(SizedString::new(*b"abc"));
+
+        assert_eq!(s.as_str(), "abc");
+        assert_eq!(s.remaining_capacity(), 0);
+
+        let s = ArrayString::from("ab".parse::<SizedString<4>>().unwrap());
+        assert_eq!(s.as_str(), "ab");
+    }
+}
@@ -0,0 +1,313 @@
+//! A NUL terminated string with a fixed inline capacity, for FFI
+
+use crate::std::{
+    ffi::{c_char, CStr},
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    str,
+};
+
+#[cfg(feature = "alloc")]
+use alloc::ffi::CString;
+
+use super::Error;
+
+#[derive(Copy, Clone)]
+#[repr(transparent)]
+/// A stack allocated C string with a capacity of `N` bytes, including its NUL terminator
+///
+/// There is always a NUL byte within the buffer, and every byte after the first NUL is zeroed, so it can be passed
+/// straight to C, or embedded in a `#[repr(C)]` struct in place of a `[c_char; N]` field.
+///
+/// # Examples
+///
+/// ```
+/// # use std::ffi::{c_char, CStr};
+/// # use quork::sized_string::SizedCStr;
+/// // Such as the `sysname` field of `utsname`
+/// let field: [c_char; 8] = [76, 105, 110, 117, 120, 0, 0, 0];
+///
+/// let sysname = SizedCStr::<8>::from_c_chars(&field).unwrap();
+/// assert_eq!(sysname.to_str(), Ok("Linux"));
+/// assert_eq!(sysname.as_c_str(), CStr::from_bytes_with_nul(b"Linux\0").unwrap());
+/// ```
+pub struct SizedCStr<const N: usize>([u8; N]);
+
+impl<const N: usize> SizedCStr<N> {
+    #[must_use]
+    /// Construct a new, empty [`SizedCStr`]
+    ///
+    /// # Panics
+    /// - If `N` is zero, as there is no room for the NUL terminator
+    pub const fn new() -> Self {
+        assert!(N > 0, "SizedCStr needs room for a NUL terminator");
+
+        Self([0; N])
+    }
+
+    /// Construct a new [`SizedCStr`] from a byte array, which must contain a NUL byte
+    ///
+    /// Every byte after the first NUL is zeroed.
+    ///
+    /// # Errors
+    /// - The bytes contain no NUL byte
+    pub const fn from_bytes(mut bytes: [u8; N]) -> Result<Self, Error> {
+        let Some(len) = nul_position(&bytes) else {
+            return Err(Error::MissingNul { capacity: N });
+        };
+
+        let mut i = len;
+        while i < N {
+            bytes[i] = 0;
+            i += 1;
+        }
+
+        Ok(Self(bytes))
+    }
+
+    /// Construct a new [`SizedCStr`] from a C `char` array, such as a field of a C struct
+    ///
+    /// The string ends at the first NUL, which must be within the slice. The slice may be any length, as long as the
+    /// string and its terminator fit in `N` bytes.
+    ///
+    /// # Errors
+    /// - The slice contains no NUL
+    /// - The string does not fit in the capacity
+    pub fn from_c_chars(chars: &[c_char]) -> Result<Self, Error> {
+        let len = chars
+            .iter()
+            .position(|c| *c == 0)
+            .ok_or(Error::MissingNul {
+                capacity: chars.len(),
+            })?;
+
+        if len >= N {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: len + 1,
+            });
+        }
+
+        let mut bytes = [0; N];
+        for (byte, c) in bytes.iter_mut().zip(&chars[..len]) {
+            *byte = u8::from_ne_bytes(c.to_ne_bytes());
+        }
+
+        Ok(Self(bytes))
+    }
+
+    #[must_use]
+    /// Get the string as a [`CStr`]
+    pub fn as_c_str(&self) -> &CStr {
+        // SAFETY: The bytes up to and including the first NUL are a valid C string
+        unsafe { CStr::from_bytes_with_nul_unchecked(&self.0[..=self.len()]) }
+    }
+
+    #[must_use]
+    /// Get a pointer to the string, for passing to C
+    ///
+    /// The pointer is valid for as long as the string is not moved or dropped.
+    pub const fn as_ptr(&self) -> *const c_char {
+        self.0.as_ptr().cast()
+    }
+
+    #[must_use]
+    /// Get the string as a C `char` array, for copying into a C struct
+    pub fn to_c_chars(&self) -> [c_char; N] {
+        self.0.map(|byte| c_char::from_ne_bytes(byte.to_ne_bytes()))
+    }
+
+    #[must_use]
+    /// The length of the string in bytes, excluding the NUL terminator
+    pub const fn len(&self) -> usize {
+        match nul_position(&self.0) {
+            Some(len) => len,
+            None => unreachable!(),
+        }
+    }
+
+    #[must_use]
+    /// Checks if the string is empty
+    pub const fn is_empty(&self) -> bool {
+        self.0[0] == 0
+    }
+
+    #[must_use]
+    /// Get the underlying bytes, including the NUL terminator and padding
+    pub const fn as_bytes(&self) -> &[u8; N] {
+        &self.0
+    }
+
+    /// Get the string as a [`str`]
+    ///
+    /// # Errors
+    /// - The string is not valid UTF-8
+    pub fn to_str(&self) -> Result<&str, str::Utf8Error> {
+        self.as_c_str().to_str()
+    }
+}
+
+const fn nul_position(bytes: &[u8]) -> Option<usize> {
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == 0 {
+            return Some(i);
+        }
+        i += 1;
+    }
+
+    None
+}
+
+impl<const N: usize> Default for SizedCStr<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const N: usize> Deref for SizedCStr<N> {
+    type Target = CStr;
+
+    fn deref(&self) -> &Self::Target {
+        self.as_c_str()
+    }
+}
+
+impl<const N: usize> AsRef<CStr> for SizedCStr<N> {
+    fn as_ref(&self) -> &CStr {
+        self
+    }
+}
+
+impl<const N: usize> PartialEq for SizedCStr<N> {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_c_str() == other.as_c_str()
+    }
+}
+
+impl<const N: usize> Eq for SizedCStr<N> {}
+
+impl<const N: usize> Hash for SizedCStr<N> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_c_str().hash(state);
+    }
+}
+
+impl<const N: usize> fmt::Debug for SizedCStr<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("SizedCStr").field(&self.as_c_str()).finish()
+    }
+}
+
+impl<const N: usize> TryFrom<&CStr> for SizedCStr<N> {
+    type Error = Error;
+
+    fn try_from(s: &CStr) -> Result<Self, Self::Error> {
+        let with_nul = s.to_bytes_with_nul();
+        if with_nul.len() > N {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: with_nul.len(),
+            });
+        }
+
+        let mut bytes = [0; N];
+        bytes[..with_nul.len()].copy_from_slice(with_nul);
+
+        Ok(Self(bytes))
+    }
+}
+
+impl<const N: usize> TryFrom<&str> for SizedCStr<N> {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        if let Some(pos) = nul_position(s.as_bytes()) {
+            return Err(Error::InteriorNul(pos));
+        }
+
+        if s.len() >= N {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: s.len() + 1,
+            });
+        }
+
+        let mut bytes = [0; N];
+        bytes[..s.len()].copy_from_slice(s.as_bytes());
+
+        Ok(Self(bytes))
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<const N: usize> From<SizedCStr<N>> for CString {
+    fn from(s: SizedCStr<N>) -> Self {
+        s.as_c_str().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let s = SizedCStr::from_bytes(*b"ab\0cd").unwrap();
+        assert_eq!(s.len(), 2);
+        assert_eq!(s.as_bytes(), b"ab\0\0\0");
+        assert_eq!(s.to_str(), Ok("ab"));
+
+        assert_eq!(
+            SizedCStr::from_bytes(*b"abc"),
+            Err(Error::MissingNul { capacity: 3 })
+        );
+        assert!(SizedCStr::<4>::new().is_empty());
+    }
+
+    #[test]
+    fn test_c_chars() {
+        let chars: [c_char; 6] = [104, 105, 0, 33, 33, 0];
+
+        let s = SizedCStr::<3>::from_c_chars(&chars).unwrap();
+        assert_eq!(s.to_str(), Ok("hi"));
+        assert_eq!(s.to_c_chars(), [104, 105, 0]);
+        assert_eq!(
+            SizedCStr::<2>::from_c_chars(&chars),
+            Err(Error::CapacityExceeded {
+                capacity: 2,
+                required: 3
+            })
+        );
+        assert_eq!(
+            SizedCStr::<8>::from_c_chars(&chars[3..5]),
+            Err(Error::MissingNul { capacity: 2 })
+        );
+    }
+
+    #[test]
+    fn test_conversions() {
+        let c_str = CStr::from_bytes_with_nul(b"hello\0").unwrap();
+
+        let s = SizedCStr::<6>::try_from(c_str).unwrap();
+        assert_eq!(&*s, c_str);
+        assert!(SizedCStr::<5>::try_from(c_str).is_err());
+
+        assert_eq!(
+            SizedCStr::<8>::try_from("hello").unwrap(),
+            c_str.try_into().unwrap()
+        );
+        assert_eq!(
+            SizedCStr::<8>::try_from("he\0llo"),
+            Err(Error::InteriorNul(2))
+        );
+
+        #[cfg(feature = "alloc")]
+        assert_eq!(CString::from(s).as_c_str(), c_str);
+
+        // SAFETY: The pointer is to a NUL terminated string, which outlives the borrow
+        let from_ptr = unsafe { CStr::from_ptr(s.as_ptr()) };
+        assert_eq!(from_ptr, c_str);
+    }
+}
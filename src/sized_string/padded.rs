@@ -0,0 +1,232 @@
+//! Fixed-width fields, padded to their width with a pad byte
+
+use crate::std::{fmt, str::FromStr};
+
+use super::{Error, SizedString};
+
+/// A left justified field, padded on the right
+pub type LeftJustified<const N: usize, const PAD: u8 = b' '> = Padded<N, PAD, false>;
+
+/// A right justified field, padded on the left
+pub type RightJustified<const N: usize, const PAD: u8 = b' '> = Padded<N, PAD, true>;
+
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[repr(transparent)]
+/// A fixed-width field of `N` bytes, padded with `PAD`
+///
+/// The value is on the left and padded on the right, or on the right and padded on the left if `RIGHT` is `true`.
+/// Use [`LeftJustified`] and [`RightJustified`] rather than naming this type directly.
+///
+/// The field is stored padded, with the same layout as a [`SizedString`], so records made of fields can be read
+/// directly as `#[repr(C)]` structs.
+/// Parsing strips any padding before re-padding the value, and [`Display`](fmt::Display) writes the padded field.
+///
+/// Padding is stripped greedily, so a value that starts or ends with the pad byte on its padded side loses it.
+/// For example, `0` in a [`RightJustified`] field padded with `b'0'` reads back as an empty value.
+///
+/// # Examples
+///
+/// ```
+/// # use quork::sized_string::{LeftJustified, RightJustified};
+/// #[repr(C)]
+/// struct Record {
+///     name: LeftJustified<8>,
+///     amount: RightJustified<6, b'0'>,
+/// }
+///
+/// let record = Record {
+///     name: "quork".parse().unwrap(),
+///     amount: "1250".parse().unwrap(),
+/// };
+///
+/// assert_eq!(record.name.value(), "quork");
+/// assert_eq!(format!("{}{}", record.name, record.amount), "quork   001250");
+/// assert_eq!(std::mem::size_of::<Record>(), 14);
+/// ```
+pub struct Padded<const N: usize, const PAD: u8, const RIGHT: bool>(SizedString<N>);
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> Padded<N, PAD, RIGHT> {
+    /// Fails to compile if the pad byte would make the field invalid UTF-8
+    const PAD_IS_ASCII: () = assert!(PAD.is_ascii(), "the pad byte must be ASCII");
+
+    /// Construct a new field, padding the value to `N` bytes
+    ///
+    /// # Errors
+    /// - The value is longer than `N` bytes
+    pub const fn new(value: &str) -> Result<Self, Error> {
+        let () = Self::PAD_IS_ASCII;
+
+        let value = value.as_bytes();
+        if value.len() > N {
+            return Err(Error::CapacityExceeded {
+                capacity: N,
+                required: value.len(),
+            });
+        }
+
+        let offset = if RIGHT { N - value.len() } else { 0 };
+
+        let mut bytes = [PAD; N];
+        let mut i = 0;
+        while i < value.len() {
+            bytes[offset + i] = value[i];
+            i += 1;
+        }
+
+        // SAFETY: The value is valid UTF-8, and is only surrounded by the ASCII pad byte
+        Ok(Self(unsafe { SizedString::new_unchecked(bytes) }))
+    }
+
+    /// Construct a field from its padded bytes, such as a field of a record
+    ///
+    /// # Errors
+    /// - The slice is not exactly `N` bytes long
+    /// - The slice is not valid UTF-8
+    pub const fn try_from_bytes(bytes: &[u8]) -> Result<Self, Error> {
+        let () = Self::PAD_IS_ASCII;
+
+        match SizedString::try_from_bytes(bytes) {
+            Ok(s) => Ok(Self(s)),
+            Err(e) => Err(e),
+        }
+    }
+
+    #[must_use]
+    /// Get the value, without its padding
+    pub fn value(&self) -> &str {
+        let pad = PAD as char;
+
+        if RIGHT {
+            self.0.as_str().trim_start_matches(pad)
+        } else {
+            self.0.as_str().trim_end_matches(pad)
+        }
+    }
+
+    #[must_use]
+    /// Get the padded field
+    pub const fn as_sized_string(&self) -> &SizedString<N> {
+        &self.0
+    }
+
+    #[must_use]
+    /// Get the padded bytes of the field
+    pub const fn as_bytes(&self) -> &[u8; N] {
+        self.0.as_bytes()
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> Default for Padded<N, PAD, RIGHT> {
+    fn default() -> Self {
+        match Self::new("") {
+            Ok(field) => field,
+            Err(_) => unreachable!(),
+        }
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> FromStr for Padded<N, PAD, RIGHT> {
+    type Err = Error;
+
+    /// Parses a value, which may already be padded
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let pad = PAD as char;
+
+        if RIGHT {
+            Self::new(s.trim_start_matches(pad))
+        } else {
+            Self::new(s.trim_end_matches(pad))
+        }
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> TryFrom<&str> for Padded<N, PAD, RIGHT> {
+    type Error = Error;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> fmt::Display for Padded<N, PAD, RIGHT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.0.as_str())
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> fmt::Debug for Padded<N, PAD, RIGHT> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("Padded").field(&self.0.as_str()).finish()
+    }
+}
+
+impl<const N: usize, const PAD: u8, const RIGHT: bool> From<Padded<N, PAD, RIGHT>>
+    for SizedString<N>
+{
+    fn from(field: Padded<N, PAD, RIGHT>) -> Self {
+        field.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_padding() {
+        let left = LeftJustified::<6>::new("abc").unwrap();
+        assert_eq!(left.as_bytes(), b"abc   ");
+        assert_eq!(left.value(), "abc");
+
+        let right = RightJustified::<6, b'0'>::new("42").unwrap();
+        assert_eq!(right.to_string(), "000042");
+        assert_eq!(right.value(), "42");
+
+        assert_eq!(LeftJustified::<6, b'*'>::default().to_string(), "******");
+        assert_eq!(
+            LeftJustified::<2>::new("abc"),
+            Err(Error::CapacityExceeded {
+                capacity: 2,
+                required: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse() {
+        // Parsing strips any padding first, so padded and unpadded input match
+        let padded: RightJustified<6> = "    ab".parse().unwrap();
+        let unpadded: RightJustified<6> = "ab".parse().unwrap();
+        assert_eq!(padded, unpadded);
+        assert_eq!(padded.to_string(), "    ab");
+
+        // Only the padded side is stripped
+        let left: LeftJustified<8> = " a b    ".parse().unwrap();
+        assert_eq!(left.value(), " a b");
+        assert!("too long".parse::<LeftJustified<4>>().is_err());
+
+        let numbers =
+            ["0100", "0020", "0003"].map(|n| n.parse::<RightJustified<4, b'0'>>().unwrap());
+        assert!(numbers[2] < numbers[1] && numbers[1] < numbers[0]);
+    }
+
+    #[test]
+    fn test_record() {
+        #[repr(C)]
+        struct Record {
+            id: RightJustified<4, b'0'>,
+            name: LeftJustified<6>,
+        }
+
+        let raw = b"0042quork ";
+        let record = Record {
+            id: RightJustified::try_from_bytes(&raw[..4]).unwrap(),
+            name: LeftJustified::try_from_bytes(&raw[4..]).unwrap(),
+        };
+
+        assert_eq!(std::mem::size_of::<Record>(), raw.len());
+        assert_eq!(record.id.value(), "42");
+        assert_eq!(record.name.value(), "quork");
+        assert_eq!(format!("{}{}", record.id, record.name).as_bytes(), raw);
+    }
+}
@@ -0,0 +1,171 @@
+//! Serde support for [`SizedString`]
+
+use crate::std::{fmt, str, str::FromStr};
+
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+
+use super::SizedString;
+
+impl<const N: usize> Serialize for SizedString<N> {
+    /// Serializes the string without its trailing NUL padding
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.trimmed())
+    }
+}
+
+impl<'de, const N: usize> Deserialize<'de> for SizedString<N> {
+    /// Deserializes a string of up to `N` bytes, padding it with NUL bytes
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_str(StrVisitor)
+    }
+}
+
+struct StrVisitor<const N: usize>;
+
+impl<const N: usize> Visitor<'_> for StrVisitor<N> {
+    type Value = SizedString<N>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a string of at most {N} bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Self::Value, E> {
+        SizedString::from_str(v).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+        let s = str::from_utf8(v).map_err(|e| E::custom(super::Error::from(e)))?;
+
+        self.visit_str(s)
+    }
+}
+
+pub mod fixed_bytes {
+    //! Serializes a [`SizedString`] as exactly `N` bytes, including its NUL padding
+    //!
+    //! This suits binary formats, which can then encode the string without a length prefix.
+    //! Use it with `#[serde(with = "quork::sized_string::fixed_bytes")]`.
+    //!
+    //! # Examples
+    //!
+    //! ```
+    //! # use quork::sized_string::SizedString;
+    //! #[derive(serde::Serialize, serde::Deserialize)]
+    //! struct Record {
+    //!     #[serde(with = "quork::sized_string::fixed_bytes")]
+    //!     name: SizedString<4>,
+    //! }
+    //!
+    //! let record = Record { name: "ab".parse().unwrap() };
+    //! let json = serde_json::to_string(&record).unwrap();
+    //!
+    //! assert_eq!(json, r#"{"name":[97,98,0,0]}"#);
+    //! ```
+
+    use crate::std::fmt;
+
+    use serde::{
+        de::{self, SeqAccess, Visitor},
+        ser::SerializeTuple,
+        Deserializer, Serializer,
+    };
+
+    use super::SizedString;
+
+    /// Serializes the string as a tuple of `N` bytes
+    ///
+    /// # Errors
+    /// - The serializer fails
+    pub fn serialize<S: Serializer, const N: usize>(
+        s: &SizedString<N>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let mut tuple = serializer.serialize_tuple(N)?;
+        for byte in s.as_bytes() {
+            tuple.serialize_element(byte)?;
+        }
+        tuple.end()
+    }
+
+    /// Deserializes the string from a tuple of `N` bytes
+    ///
+    /// # Errors
+    /// - The input is not exactly `N` bytes
+    /// - The bytes are not valid UTF-8
+    pub fn deserialize<'de, D: Deserializer<'de>, const N: usize>(
+        deserializer: D,
+    ) -> Result<SizedString<N>, D::Error> {
+        deserializer.deserialize_tuple(N, BytesVisitor)
+    }
+
+    struct BytesVisitor<const N: usize>;
+
+    impl<'de, const N: usize> Visitor<'de> for BytesVisitor<N> {
+        type Value = SizedString<N>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "{N} bytes of UTF-8")
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = [0; N];
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte = seq
+                    .next_element()?
+                    .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+            }
+
+            if seq.next_element::<u8>()?.is_some() {
+                return Err(de::Error::invalid_length(N + 1, &self));
+            }
+
+            SizedString::try_from_bytes(&bytes).map_err(de::Error::custom)
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Self::Value, E> {
+            SizedString::try_from_bytes(v).map_err(E::custom)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Config {
+        name: SizedString<8>,
+        #[serde(with = "super::fixed_bytes")]
+        tag: SizedString<4>,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let config = Config {
+            name: "quork".parse().unwrap(),
+            tag: "v1".parse().unwrap(),
+        };
+
+        let json = serde_json::to_string(&config).unwrap();
+        assert_eq!(json, r#"{"name":"quork","tag":[118,49,0,0]}"#);
+        assert_eq!(serde_json::from_str::<Config>(&json).unwrap(), config);
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = serde_json::from_str::<Config>(r#"{"name":"much too long","tag":[0,0,0,0]}"#)
+            .unwrap_err();
+        assert!(err
+            .to_string()
+            .starts_with("Capacity of 8 bytes exceeded, 13 bytes required"));
+
+        let err = serde_json::from_str::<Config>(r#"{"name":"","tag":[255,0,0,0]}"#).unwrap_err();
+        assert!(err.to_string().starts_with("Invalid UTF-8"));
+
+        let err = serde_json::from_str::<Config>(r#"{"name":"","tag":[0,0,0]}"#).unwrap_err();
+        assert!(err.to_string().starts_with("invalid length 3"));
+    }
+}
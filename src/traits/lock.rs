@@ -19,22 +19,10 @@ pub trait LockMap<T: ?Sized> {
     }
 }
 
-impl<'a, T> LockMap<T> for std::sync::MutexGuard<'a, T> {}
+impl<T> LockMap<T> for std::sync::MutexGuard<'_, T> {}
 
 #[cfg(feature = "spin")]
-impl<'a, T> LockMap<T> for spin::mutex::MutexGuard<'a, T> {}
+impl<T> LockMap<T> for spin::mutex::MutexGuard<'_, T> {}
 
 #[cfg(feature = "lock_api")]
-impl<'a, T, G: lock_api::RawMutex> LockMap<T> for lock_api::MutexGuard<'a, G, T> {}
-
-#[cfg(test)]
-mod tests {
-    use crate::prelude::*;
-
-    static MUTEX: std::sync::Mutex<bool> = std::sync::Mutex::new(false);
-
-    #[test]
-    fn test_lock_map() {
-        MUTEX.lock().map(|mut m| m.flip()).unwrap();
-    }
-}
+impl<T, G: lock_api::RawMutex> LockMap<T> for lock_api::MutexGuard<'_, G, T> {}
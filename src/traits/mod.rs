@@ -19,6 +19,7 @@ pub mod prelude {
         // TODO: Get these working without std crate (as much as possible)
         if #[cfg(feature = "std")] {
             pub use super::flip::*;
+            #[allow(deprecated)]
             pub use super::lock::*;
             pub use super::truncate::*;
             pub use super::map::*;
@@ -28,3 +29,17 @@ pub mod prelude {
     pub use super::list::*;
     pub use super::truthy::*;
 }
+
+// Tests of the deprecated `lock` module live here, as the test harness itself would warn about tests within it
+#[cfg(all(test, feature = "std"))]
+#[allow(deprecated)]
+mod tests {
+    use super::prelude::*;
+
+    static MUTEX: std::sync::Mutex<bool> = std::sync::Mutex::new(false);
+
+    #[test]
+    fn test_lock_map() {
+        MUTEX.lock().map(|mut m| m.flip()).unwrap();
+    }
+}
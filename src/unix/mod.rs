@@ -1,3 +1,5 @@
 //! Unix specific functionality.
 
+#[cfg(all(feature = "network", target_os = "linux"))]
+pub mod network;
 pub mod root;
@@ -0,0 +1,224 @@
+//! Network helpers
+
+use std::net::IpAddr;
+
+use crate::network::{
+    interface::Interface, provider::is_forced_offline_by_env, route::Route, IpVersion,
+};
+
+#[derive(Debug, thiserror::Error)]
+/// Errors when getting the current connectivity
+pub enum Error {
+    #[error("IO error: {0}")]
+    /// The network interfaces could not be read
+    Io(#[from] std::io::Error),
+    #[error("Route error: {0}")]
+    /// The routing table could not be read
+    Route(#[from] crate::network::route::Error),
+}
+
+/// The level of connectivity to a network
+///
+/// This mirrors the flags reported by Windows, but is derived from the routing table and interface addresses, so no
+/// traffic is sent. A default route is taken to mean internet connectivity, even if it is behind a captive portal,
+/// which can be checked with [`crate::network::portal`].
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Connectivity {
+    /// The underlying network interfaces have no connectivity to any network.
+    Disconnected = 0,
+    /// There is connectivity to a network, but the service cannot detect any IPv4 Network Traffic.
+    Ipv4Notraffic = 0x1,
+    /// There is connectivity to a network, but the service cannot detect any IPv6 Network Traffic.
+    Ipv6Notraffic = 0x2,
+    /// There is connectivity to the local subnet using the IPv4 protocol.
+    Ipv4Subnet = 0x10,
+    /// There is connectivity to a routed network using the IPv4 protocol.
+    Ipv4Localnetwork = 0x20,
+    /// There is connectivity to the Internet using the IPv4 protocol.
+    Ipv4Internet = 0x40,
+    /// There is connectivity to the local subnet using the IPv6 protocol.
+    Ipv6Subnet = 0x100,
+    /// There is connectivity to a local network using the IPv6 protocol.
+    Ipv6Localnetwork = 0x200,
+    /// There is connectivity to the Internet using the IPv6 protocol.
+    Ipv6Internet = 0x400,
+}
+
+impl Connectivity {
+    #[must_use]
+    /// Gets the current connectivity to a network.
+    ///
+    /// # Panics
+    /// - If the underlying [`Connectivity::try_get()`] method returns an error
+    pub fn get() -> Self {
+        Self::try_get().unwrap()
+    }
+
+    /// Tries to get the current connectivity to a network.
+    ///
+    /// Reports [`Connectivity::Disconnected`] if forced offline by [`FORCE_OFFLINE_VAR`](crate::network::provider::FORCE_OFFLINE_VAR).
+    ///
+    /// # Errors
+    /// - The routing table could not be read
+    /// - The network interfaces could not be read
+    pub fn try_get() -> Result<Self, Error> {
+        if is_forced_offline_by_env() {
+            return Ok(Self::Disconnected);
+        }
+
+        Self::detect()
+    }
+
+    /// Gets the real connectivity, ignoring [`FORCE_OFFLINE_VAR`](crate::network::provider::FORCE_OFFLINE_VAR)
+    pub(crate) fn detect() -> Result<Self, Error> {
+        let routes = crate::network::routes()?;
+        let interfaces = crate::network::interfaces()?;
+
+        Ok(Self::from_parts(&routes, &interfaces))
+    }
+
+    #[must_use]
+    /// Derives the connectivity from a routing table and the host's interfaces
+    ///
+    /// IPv4 is preferred over IPv6 when both have the same level of connectivity.
+    pub fn from_parts(routes: &[Route], interfaces: &[Interface]) -> Self {
+        let is_external = |name: &str| {
+            interfaces
+                .iter()
+                .any(|iface| iface.name == name && iface.up && !iface.loopback)
+        };
+        let has_route = |version: IpVersion, default: bool| {
+            routes.iter().any(|route| {
+                let matches = if default {
+                    route.is_default()
+                } else {
+                    route.gateway.is_some()
+                };
+
+                matches && route.ip_version() == version && is_external(&route.interface)
+            })
+        };
+        let has_address = |version: IpVersion| {
+            interfaces
+                .iter()
+                .filter(|iface| iface.up && !iface.loopback)
+                .flat_map(|iface| iface.addresses_for(version))
+                .any(|address| !is_link_local(address.addr))
+        };
+
+        if has_route(IpVersion::V4, true) {
+            Connectivity::Ipv4Internet
+        } else if has_route(IpVersion::V6, true) {
+            Connectivity::Ipv6Internet
+        } else if has_route(IpVersion::V4, false) {
+            Connectivity::Ipv4Localnetwork
+        } else if has_route(IpVersion::V6, false) {
+            Connectivity::Ipv6Localnetwork
+        } else if has_address(IpVersion::V4) {
+            Connectivity::Ipv4Subnet
+        } else if has_address(IpVersion::V6) {
+            Connectivity::Ipv6Subnet
+        } else {
+            Connectivity::Disconnected
+        }
+    }
+
+    #[must_use]
+    /// Gets the version of the connected network
+    ///
+    /// Returns `None` if the version could not be determined.
+    pub fn ip_version(&self) -> Option<IpVersion> {
+        match self {
+            Connectivity::Ipv4Internet
+            | Connectivity::Ipv4Localnetwork
+            | Connectivity::Ipv4Subnet
+            | Connectivity::Ipv4Notraffic => Some(IpVersion::V4),
+            Connectivity::Ipv6Internet
+            | Connectivity::Ipv6Localnetwork
+            | Connectivity::Ipv6Subnet
+            | Connectivity::Ipv6Notraffic => Some(IpVersion::V6),
+            Connectivity::Disconnected => None,
+        }
+    }
+}
+
+fn is_link_local(addr: IpAddr) -> bool {
+    match addr {
+        IpAddr::V4(addr) => addr.is_link_local(),
+        IpAddr::V6(addr) => addr.segments()[0] & 0xffc0 == 0xfe80,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::network::InterfaceAddress;
+
+    use super::*;
+
+    fn interface(name: &str, addr: &str) -> Interface {
+        Interface {
+            name: name.to_string(),
+            index: 0,
+            up: true,
+            loopback: name == "lo",
+            point_to_point: false,
+            addresses: vec![InterfaceAddress {
+                addr: addr.parse().unwrap(),
+                prefix_len: 24,
+            }],
+            tunnel: None,
+        }
+    }
+
+    fn route(destination: &str, gateway: Option<&str>, interface: &str) -> Route {
+        Route {
+            destination: destination.parse().unwrap(),
+            gateway: gateway.map(|gateway| gateway.parse().unwrap()),
+            interface: interface.to_string(),
+            metric: 0,
+        }
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let lo = interface("lo", "127.0.0.1");
+        let eth0 = interface("eth0", "192.168.1.20");
+
+        assert_eq!(
+            Connectivity::from_parts(&[], std::slice::from_ref(&lo)),
+            Connectivity::Disconnected
+        );
+        assert_eq!(
+            Connectivity::from_parts(&[], &[lo.clone(), interface("eth0", "169.254.3.4")]),
+            Connectivity::Disconnected
+        );
+        assert_eq!(
+            Connectivity::from_parts(&[], &[lo.clone(), eth0.clone()]),
+            Connectivity::Ipv4Subnet
+        );
+
+        let interfaces = [lo, eth0];
+        let lan = route("10.0.0.0/8", Some("192.168.1.1"), "eth0");
+        let default = route("0.0.0.0/0", Some("192.168.1.1"), "eth0");
+        let default_v6 = route("::/0", Some("fe80::1"), "eth0");
+
+        assert_eq!(
+            Connectivity::from_parts(std::slice::from_ref(&lan), &interfaces),
+            Connectivity::Ipv4Localnetwork
+        );
+        assert_eq!(
+            Connectivity::from_parts(&[lan.clone(), default_v6.clone()], &interfaces),
+            Connectivity::Ipv6Internet
+        );
+        assert_eq!(
+            Connectivity::from_parts(&[lan, default_v6, default], &interfaces),
+            Connectivity::Ipv4Internet
+        );
+    }
+
+    #[test]
+    fn test_get() {
+        assert!(Connectivity::try_get().is_ok());
+    }
+}
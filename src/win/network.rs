@@ -16,7 +16,7 @@ use windows::Win32::Networking::NetworkListManager::{
     NLM_CONNECTIVITY_IPV6_SUBNET,
 };
 
-use crate::network::IpVersion;
+use crate::network::{provider::is_forced_offline_by_env, IpVersion};
 
 use super::ComInit;
 
@@ -24,6 +24,7 @@ use super::ComInit;
 ///
 /// The meaning of these can be confusing. See this article for more information: <https://devblogs.microsoft.com/oldnewthing/20230112-00/?p=107700>
 #[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Connectivity {
     /// The underlying network interfaces have no connectivity to any network.
     Disconnected = 0,
@@ -57,10 +58,21 @@ impl Connectivity {
 
     /// Tries to get the current connectivity to a network.
     ///
+    /// Reports [`Connectivity::Disconnected`] if forced offline by [`FORCE_OFFLINE_VAR`](crate::network::provider::FORCE_OFFLINE_VAR).
+    ///
     /// # Errors
     /// - Can fail for any of the many reasons the internal windows API could fail
     /// - Can fail if the network result is invalid
     pub fn try_get() -> windows::core::Result<Self> {
+        if is_forced_offline_by_env() {
+            return Ok(Self::Disconnected);
+        }
+
+        Self::detect()
+    }
+
+    /// Gets the real connectivity, ignoring [`FORCE_OFFLINE_VAR`](crate::network::provider::FORCE_OFFLINE_VAR)
+    pub(crate) fn detect() -> windows::core::Result<Self> {
         unsafe {
             let manager = get_networklist_manager()?;
             get_connectivity(&manager)
@@ -0,0 +1,65 @@
+// The macro output and the types it uses must not need `std`
+#![no_std]
+
+use quork::sized_string::SizedString;
+use quork_proc::sized_string;
+
+#[test]
+fn test_utf8() {
+    let s: SizedString<6> = sized_string!("héllo");
+    assert_eq!(s.as_str(), "héllo");
+
+    let s = sized_string!("🦀");
+    assert_eq!(s.len(), 4);
+    assert_eq!(s.as_str(), "🦀");
+}
+
+#[test]
+fn test_byte_string() {
+    let s = sized_string!(b"bytes");
+    assert_eq!(s.as_str(), "bytes");
+
+    let s = sized_string!(b"h\xc3\xa9");
+    assert_eq!(s.as_str(), "hé");
+}
+
+#[test]
+fn test_concat() {
+    let s = sized_string!("version ", 1, '.', 2, " ", true, b"!");
+    assert_eq!(s.as_str(), concat!("version ", 1, '.', 2, " ", true, "!"));
+}
+
+#[test]
+fn test_capacity() {
+    let s: SizedString<8> = sized_string!(8, "abc");
+    assert_eq!(s.as_bytes(), b"abc\0\0\0\0\0");
+
+    let s: SizedString<3> = sized_string!(3, "a", "bc");
+    assert_eq!(s.as_str(), "abc");
+
+    // A leading integer is the capacity, so a leading number must be a string literal
+    let s: SizedString<3> = sized_string!("1", '.', 2);
+    assert_eq!(s.as_str(), "1.2");
+
+    // On its own, an integer is the string rather than the capacity
+    let s: SizedString<2> = sized_string!(32);
+    assert_eq!(s.as_str(), "32");
+}
+
+#[test]
+fn test_const() {
+    const GREETING: SizedString<5> = sized_string!("hello");
+    assert_eq!(GREETING.as_str(), "hello");
+}
+
+#[test]
+fn test_const_concat() {
+    const ID: SizedString<11> = quork::concat_sized!(
+        sized_string!("quork").to_ascii_uppercase(),
+        "::",
+        sized_string!("v", 1, '.', 2)
+    );
+
+    assert_eq!(ID, "QUORK::v1.2");
+    assert_eq!(ID.slice::<5>(0), "QUORK");
+}